@@ -0,0 +1,86 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use parsley::chomsky::{Grammar, Options};
+use parsley::cyk::Cyk;
+
+#[allow(dead_code)]
+#[path = "../src/bnf/words.rs"]
+mod words;
+
+/// grammars of the bundled rules, with their start rule and a long accepted word
+fn cases() -> Vec<(&'static str, Grammar, String)> {
+    let div3 = include_str!("../rules/binary-div3");
+    let scream = include_str!("../rules/scream");
+    let long_scream = include_str!("../rules/long-scream");
+    let grammar = |source, start| Grammar::from_source(source, &Options::new().start(start));
+    vec![
+        // 0b011 repeated is divisible by three
+        ("binary-div3", grammar(div3, "0"), "011".repeat(20)),
+        ("scream", grammar(scream, "scream"), "aA".repeat(30)),
+        (
+            "long-scream",
+            grammar(long_scream, "long-scream"),
+            "Aa".repeat(30),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, grammar, word)| (name, grammar.unwrap(), word))
+    .collect()
+}
+
+fn accepts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("accepts");
+    group.sample_size(10);
+    for (name, grammar, word) in cases() {
+        assert!(grammar.accepts(&word));
+        let cyk = Cyk::new(&grammar);
+        assert!(word.len() <= Cyk::MATRIX_THRESHOLD);
+        group.bench_with_input(BenchmarkId::new("naive", name), &word, |b, w| {
+            b.iter(|| grammar.accepts_naive(w))
+        });
+        group.bench_with_input(BenchmarkId::new("bitset", name), &word, |b, w| {
+            b.iter(|| cyk.accepts(w))
+        });
+    }
+    group.finish();
+}
+
+fn accepts_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("accepts_all");
+    group.sample_size(10);
+    for (name, grammar, word) in cases() {
+        let alphabet = word.chars().take(2).collect::<String>();
+        let words = words::words(&alphabet, 12);
+        let cyk = Cyk::new(&grammar);
+        group.bench_with_input(BenchmarkId::new("each", name), &words, |b, words| {
+            b.iter(|| words.iter().map(|w| cyk.accepts(w)).collect::<Vec<_>>())
+        });
+        group.bench_with_input(BenchmarkId::new("batch", name), &words, |b, words| {
+            b.iter(|| cyk.accepts_all(words))
+        });
+    }
+    group.finish();
+}
+
+/// the matrix is faster for binary-div3 and the table for both screams, which `accepts` should
+/// pick by itself
+fn long_words(c: &mut Criterion) {
+    let mut group = c.benchmark_group("long_words");
+    group.sample_size(10);
+    for (name, grammar, word) in cases() {
+        let word = word.repeat(8);
+        let cyk = Cyk::new(&grammar);
+        group.bench_with_input(BenchmarkId::new("auto", name), &word, |b, w| {
+            b.iter(|| cyk.accepts(w))
+        });
+        group.bench_with_input(BenchmarkId::new("table", name), &word, |b, w| {
+            b.iter(|| cyk.accepts_table(w))
+        });
+        group.bench_with_input(BenchmarkId::new("matrix", name), &word, |b, w| {
+            b.iter(|| cyk.accepts_matrix(w))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, accepts, accepts_all, long_words);
+criterion_main!(benches);
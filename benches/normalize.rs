@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use parsley::chomsky::{Grammar, Options};
+
+/// `copies` renamed copies of the Java subset, with a rule that chooses between them
+///
+/// Rule names get the number of their copy appended, test directives are left out.
+fn java(copies: usize) -> String {
+    let source = include_str!("../rules/java-subset");
+    let mut all = (0..copies)
+        .map(|k| format!("<compilation-unit-{}>", k))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    all = format!("<all> <= {}\n", all);
+    for k in 0..copies {
+        for line in source.lines().filter(|line| !line.starts_with('@')) {
+            let mut escaped = false;
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                all.push(c);
+                // the terminals `<` and `>` are escaped, e.g. `\<=`
+                if c == '<' && !escaped && chars.peek().is_some_and(|c| c.is_ascii_lowercase()) {
+                    while let Some(c) = chars.next_if(|c| *c != '>') {
+                        all.push(c);
+                    }
+                    all.push_str(&format!("-{}", k));
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            all.push('\n');
+        }
+    }
+    all
+}
+
+fn normalize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("normalize");
+    group.sample_size(10);
+    for copies in [1, 5] {
+        let source = java(copies);
+        let grammar = Grammar::from_source(&source, &Options::new()).unwrap();
+        println!("java-subset x{}: {}", copies, grammar.size());
+        group.bench_with_input(BenchmarkId::new("java-subset", copies), &source, |b, s| {
+            b.iter(|| Grammar::from_source(s, &Options::new()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, normalize);
+criterion_main!(benches);
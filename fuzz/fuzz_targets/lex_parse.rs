@@ -0,0 +1,13 @@
+//! Lexes and parses arbitrary text, and formats the syntax errors
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parsley::{lex, parse};
+
+fuzz_target!(|source: &str| {
+    if let Err(errors) = parse::parse(&lex::lex(source)) {
+        for error in errors.iter() {
+            error.position(source);
+        }
+        parse::format_errors("fuzz", source, errors);
+    }
+});
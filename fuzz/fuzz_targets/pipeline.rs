@@ -0,0 +1,21 @@
+//! Builds the grammar of arbitrary rules and checks a word against it
+//!
+//! The input is the source of the rules, optionally followed by a null character and the word.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parsley::chomsky::{Grammar, Options};
+use parsley::error::Error;
+use parsley::parse;
+
+fuzz_target!(|input: &str| {
+    let (source, word) = input.split_once('\0').unwrap_or((input, ""));
+    match Grammar::from_source(source, &Options::new()) {
+        Ok(grammar) => {
+            grammar.accepts(word);
+        }
+        Err(Error::Syntax(errors)) => {
+            parse::format_errors("fuzz", source, errors);
+        }
+        Err(_) => {}
+    }
+});
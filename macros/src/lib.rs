@@ -0,0 +1,245 @@
+//! Compile-time embedding of grammars for [parsley](https://github.com/JM4ier/parsley)
+//!
+//! ```
+//! let scream = parsley_macros::grammar!(file = "../rules/scream");
+//! let digits = parsley_macros::grammar!("<digits> <= {0|1}", start = "digits");
+//! assert!(scream.accepts("aAaA"));
+//! assert!(digits.accepts("0110"));
+//! ```
+//!
+//! Rules with syntax errors don't compile:
+//!
+//! ```compile_fail
+//! let scream = parsley_macros::grammar!("<scream> <= {a|A");
+//! ```
+//!
+//! ```compile_fail
+//! let scream = parsley_macros::grammar!("<scream> <= {a|A}", start = "shout");
+//! ```
+
+use parsley::chomsky::{Definition, Grammar, Options};
+use parsley::error::Error;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use std::ops::RangeInclusive;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitStr, Token};
+
+#[cfg(test)]
+mod test;
+
+/// Where the ebnf rules of the grammar come from
+enum Source {
+    Inline(LitStr),
+    /// path relative to the manifest directory of the crate using the macro
+    File(LitStr),
+}
+
+struct Input {
+    source: Source,
+    start: Option<LitStr>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let source = if input.peek(LitStr) {
+            Source::Inline(input.parse()?)
+        } else {
+            let key: Ident = input.parse()?;
+            if key != "file" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected a string literal or `file = \"...\"`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            Source::File(input.parse()?)
+        };
+
+        let mut start = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            if key != "start" || start.is_some() {
+                return Err(syn::Error::new(key.span(), "expected `start = \"...\"`"));
+            }
+            input.parse::<Token![=]>()?;
+            start = Some(input.parse()?);
+        }
+
+        Ok(Self { source, start })
+    }
+}
+
+/// Builds a `parsley::chomsky::Grammar` from ebnf rules at compile time
+///
+/// The rules are either given inline as a string literal, or read from a file with
+/// `file = "path"`, relative to the directory of the crate's `Cargo.toml`.
+/// The top-level rule can be selected with `start = "name"`.
+///
+/// Syntax errors in the rules are reported as compiler errors.
+/// The macro expands to an expression of type `&'static parsley::chomsky::Grammar`, which is
+/// built on first use and shared by all later evaluations, so the crate using it also needs to
+/// depend on `parsley`.
+#[proc_macro]
+pub fn grammar(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as Input);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => {
+            // a block, so that several errors can be reported in expression position
+            let errors = err.to_compile_error();
+            quote!({ #errors }).into()
+        }
+    }
+}
+
+fn expand(input: &Input) -> syn::Result<TokenStream> {
+    let (source, name, include) = match &input.source {
+        Source::Inline(lit) => (lit.value(), String::from("<inline>"), quote! {}),
+        Source::File(lit) => {
+            let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| String::from("."));
+            let path = std::path::Path::new(&dir).join(lit.value());
+            let source = std::fs::read_to_string(&path).map_err(|e| {
+                syn::Error::new(
+                    lit.span(),
+                    format!("failed to read file {}: {}", path.display(), e),
+                )
+            })?;
+            let path = path.to_string_lossy().into_owned();
+            // makes cargo rebuild the crate when the rules change
+            let include = quote! { const _: &str = include_str!(#path); };
+            (source, lit.value(), include)
+        }
+    };
+
+    let options = Options {
+        start: input.start.as_ref().map(LitStr::value),
+    };
+    let grammar = Grammar::from_source(&source, &options)
+        .map_err(|err| compile_error(&err, &source, &name, &input.source))?;
+
+    let start = grammar.start;
+    let null = grammar.null;
+    let rules = grammar.rules.iter().map(|rule| {
+        let defs = rule.iter().map(|def| match def {
+            Definition::Term(t) => quote! { ::parsley::chomsky::Definition::Term(vec![#(#t),*]) },
+            Definition::Product([a, b]) => {
+                quote! { ::parsley::chomsky::Definition::Product([#a, #b]) }
+            }
+        });
+        quote! { vec![#(#defs),*] }
+    });
+
+    Ok(quote! {{
+        #include
+        static GRAMMAR: ::std::sync::OnceLock<::parsley::chomsky::Grammar> =
+            ::std::sync::OnceLock::new();
+        GRAMMAR.get_or_init(|| ::parsley::chomsky::Grammar {
+            start: #start,
+            null: #null,
+            rules: vec![#(#rules),*],
+        })
+    }})
+}
+
+/// converts an error of the pipeline into a compiler error
+///
+/// Syntax errors in inline rules point at the offending characters, if the compiler supports it.
+/// Otherwise they are reported at the literal, with their position in the rules as part of the
+/// message.
+fn compile_error(err: &Error, source: &str, name: &str, input: &Source) -> syn::Error {
+    let lit = match input {
+        Source::Inline(lit) | Source::File(lit) => lit,
+    };
+    match err {
+        Error::Syntax(errors) => {
+            let mut errors = errors.iter().map(|e| {
+                let message = match e.position(source) {
+                    Some((line, column)) => {
+                        format!("{}:{}:{}: {}", name, line, column, e.message())
+                    }
+                    None => format!("{}: {}", name, e.message()),
+                };
+                let span = match (input, e.location()) {
+                    (Source::Inline(lit), Some(location)) => span_of(lit, location),
+                    _ => lit.span(),
+                };
+                syn::Error::new(span, message)
+            });
+            let mut first = errors.next().expect("syntax errors are never empty");
+            for e in errors {
+                first.combine(e);
+            }
+            first
+        }
+        err => syn::Error::new(lit.span(), err.to_string()),
+    }
+}
+
+/// span of the characters with indices in `chars` of the value of `lit`
+///
+/// Only nightly compilers can point into literals, others get the span of the whole literal.
+fn span_of(lit: &LitStr, chars: &RangeInclusive<usize>) -> Span {
+    let token = match lit.to_token_stream().into_iter().next() {
+        Some(TokenTree::Literal(token)) => token,
+        _ => return lit.span(),
+    };
+    let code = token.to_string();
+    let start = offset(&code, *chars.start());
+    // errors at the end of the rules point at the closing quote
+    let end = offset(&code, *chars.end() + 1).or(start.map(|start| start + 1));
+    start
+        .zip(end)
+        .and_then(|(start, end)| token.subspan(start..end))
+        .unwrap_or_else(|| lit.span())
+}
+
+/// byte offset in the code of a string literal of its character with the given index
+///
+/// An index just past the last character gives the offset of the closing quote.
+fn offset(code: &str, index: usize) -> Option<usize> {
+    if let Some(raw) = code.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let start = hashes + 2;
+        let value = code.get(start..code.len().checked_sub(hashes + 1)?)?;
+        return value
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(value.len()))
+            .nth(index)
+            .map(|i| start + i);
+    }
+
+    let code = code.char_indices().collect::<Vec<_>>();
+    let end = code.len().checked_sub(1)?;
+    let mut i = 1;
+    let mut chars = 0;
+    while i < end {
+        let (pos, c) = code[i];
+        if c == '\\' && code[i + 1].1 == '\n' {
+            // a line continuation, which skips the following whitespace
+            i += 1;
+            while i < end && code[i].1.is_whitespace() {
+                i += 1;
+            }
+            continue;
+        }
+        if chars == index {
+            return Some(pos);
+        }
+        chars += 1;
+        i += 1;
+        if c == '\\' {
+            i += match code[i].1 {
+                'x' => 3,
+                'u' => code[i..].iter().position(|&(_, c)| c == '}')? + 1,
+                _ => 1,
+            };
+        }
+    }
+    (chars == index).then(|| code[end].0)
+}
@@ -0,0 +1,40 @@
+use super::*;
+
+#[test]
+fn offsets() {
+    let code = r#""<s> <= a""#;
+    assert_eq!(offset(code, 0), Some(1));
+    assert_eq!(offset(code, 7), Some(8));
+    assert_eq!(offset(code, 8), Some(9));
+    assert_eq!(offset(code, 9), None);
+
+    // escapes are several characters of code, but one of the value
+    let code = r#""<s> <= \"a\" | \x62 | \u{e4}ö\n<t>""#;
+    let value = "<s> <= \"a\" | \x62 | \u{e4}ö\n<t>";
+    let chars = value.chars().collect::<Vec<_>>();
+    for (index, expected) in [
+        (7, "\\\""),
+        (13, "\\x62"),
+        (17, "\\u{e4}"),
+        (18, "ö"),
+        (20, "<t>"),
+    ] {
+        let pos = offset(code, index).unwrap();
+        assert!(
+            code[pos..].starts_with(expected),
+            "{} {:?}",
+            index,
+            chars[index]
+        );
+    }
+    assert_eq!(offset(code, chars.len()), Some(code.len() - 1));
+
+    // line continuations don't add characters
+    let code = "\"<s> <= \\\n      a\"";
+    assert_eq!(offset(code, 7), Some(code.len() - 2));
+
+    let code = r###"r#"<s> <= "a""#"###;
+    assert_eq!(offset(code, 7), Some(10));
+    assert_eq!(offset(code, 10), Some(13));
+    assert_eq!(offset(code, 11), None);
+}
@@ -0,0 +1,30 @@
+use parsley_macros::grammar;
+
+#[test]
+fn inline() {
+    let g = grammar!("<scream> <= {a|A}");
+    assert!(g.accepts(""));
+    assert!(g.accepts("aAaA"));
+    assert!(!g.accepts("b"));
+}
+
+#[test]
+fn file_with_start() {
+    let g = grammar!(file = "../rules/binary-div3", start = "1");
+    assert!(g.accepts("100"));
+    assert!(!g.accepts("110"));
+}
+
+#[test]
+fn same_as_runtime() {
+    let source = include_str!("../../rules/long-scream");
+    let runtime = parsley::chomsky::Grammar::from_source(source, &Default::default()).unwrap();
+    assert_eq!(grammar!(file = "../rules/long-scream"), &runtime);
+}
+
+#[test]
+fn built_once() {
+    let grammars = (0..3).map(|_| grammar!("<s> <= a {b}")).collect::<Vec<_>>();
+    assert!(std::ptr::eq(grammars[0], grammars[2]));
+    assert!(grammars[1].accepts("abb"));
+}
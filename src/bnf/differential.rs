@@ -0,0 +1,246 @@
+//! Differential tests of the whole pipeline from ebnf rules to the normal form
+//!
+//! Random rules are checked against a reference matcher that works on the rules directly,
+//! and any disagreement is shrunk to a small grammar and word before it is reported.
+
+use super::words::words;
+use super::{BnfPart, BnfRule};
+use crate::chomsky::Grammar;
+use rand::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// random rules named `r0` to `r3`, where `r0` may be defined twice, that may also refer to the
+/// undefined rule `r4`
+pub(crate) fn random_rules(rng: &mut ThreadRng) -> Vec<BnfRule> {
+    fn part(rng: &mut ThreadRng, depth: usize) -> BnfPart {
+        let parts = |rng: &mut ThreadRng| {
+            (0..rng.gen_range(1..4))
+                .map(|_| part(rng, depth - 1))
+                .collect()
+        };
+        match rng.gen_range(0..if depth == 0 { 3 } else { 6 }) {
+            0 => BnfPart::Rule(format!("r{}", rng.gen_range(0..5))),
+            1 => BnfPart::Literal(["a", "b", "ab", ""][rng.gen_range(0..4)].to_string()),
+            2 => BnfPart::Empty,
+            3 => BnfPart::Choice(parts(rng)),
+            4 => BnfPart::Concat(parts(rng)),
+            _ => BnfPart::Repeat(Box::new(part(rng, depth - 1))),
+        }
+    }
+    (0..rng.gen_range(1..6))
+        .map(|i| BnfRule {
+            name: format!("r{}", i % 4),
+            def: part(rng, 3),
+        })
+        .collect()
+}
+
+/// Checks words against rules by following their definitions
+///
+/// For every rule and position, it stores the positions where a word of the rule that starts
+/// there can end. These sets only grow, so recomputing all of them until none changes gives
+/// the right sets even for left recursive rules.
+struct Matcher<'a> {
+    rules: &'a [BnfRule],
+    word: Vec<char>,
+    ends: HashMap<(&'a str, usize), BTreeSet<usize>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn accepts(rules: &'a [BnfRule], word: &str) -> bool {
+        let mut matcher = Self {
+            rules,
+            word: word.chars().collect(),
+            ends: HashMap::new(),
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in rules {
+                for i in 0..=matcher.word.len() {
+                    let ends = matcher.rule(&rule.name, i);
+                    if matcher.ends.get(&(&rule.name[..], i)) != Some(&ends) {
+                        matcher.ends.insert((&rule.name, i), ends);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        matcher.ends[&(&rules[0].name[..], 0)].contains(&matcher.word.len())
+    }
+
+    /// ends of the words of all definitions of the rule `name` that start at `i`
+    fn rule(&self, name: &str, i: usize) -> BTreeSet<usize> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.name == name)
+            .flat_map(|rule| self.part(&rule.def, i))
+            .collect()
+    }
+
+    fn part(&self, part: &BnfPart, i: usize) -> BTreeSet<usize> {
+        match part {
+            BnfPart::Empty => BTreeSet::from([i]),
+            BnfPart::Literal(s) => {
+                let s = s.chars().collect::<Vec<_>>();
+                if self.word[i..].starts_with(&s) {
+                    BTreeSet::from([i + s.len()])
+                } else {
+                    BTreeSet::new()
+                }
+            }
+            BnfPart::Rule(name) => self
+                .ends
+                .get(&(name.as_str(), i))
+                .cloned()
+                .unwrap_or_default(),
+            BnfPart::Choice(parts) => parts.iter().flat_map(|p| self.part(p, i)).collect(),
+            BnfPart::Concat(parts) => parts.iter().fold(BTreeSet::from([i]), |ends, p| {
+                ends.iter().flat_map(|&j| self.part(p, j)).collect()
+            }),
+            BnfPart::Repeat(part) => {
+                let mut ends = BTreeSet::from([i]);
+                let mut q = vec![i];
+                while let Some(j) = q.pop() {
+                    for k in self.part(part, j) {
+                        if ends.insert(k) {
+                            q.push(k);
+                        }
+                    }
+                }
+                ends
+            }
+        }
+    }
+}
+
+/// whether the normal form and the reference matcher disagree on the word
+fn disagree(rules: &[BnfRule], word: &str) -> bool {
+    let grammar = Grammar::from_rules(rules, None).unwrap();
+    grammar.accepts(word) != Matcher::accepts(rules, word)
+}
+
+/// parts that are a bit simpler than `part`
+fn smaller(part: &BnfPart) -> Vec<BnfPart> {
+    let mut parts = Vec::new();
+    if *part != BnfPart::Empty {
+        parts.push(BnfPart::Empty);
+    }
+    match part {
+        BnfPart::Literal(s) if s.chars().count() > 1 => {
+            for c in s.chars() {
+                parts.push(BnfPart::Literal(c.to_string()));
+            }
+        }
+        BnfPart::Choice(children) | BnfPart::Concat(children) => {
+            let rebuild = |children| match part {
+                BnfPart::Choice(_) => BnfPart::Choice(children),
+                _ => BnfPart::Concat(children),
+            };
+            parts.extend(children.iter().cloned());
+            for i in 0..children.len() {
+                if children.len() > 1 {
+                    let mut fewer = children.clone();
+                    fewer.remove(i);
+                    parts.push(rebuild(fewer));
+                }
+                for child in smaller(&children[i]) {
+                    let mut simpler = children.clone();
+                    simpler[i] = child;
+                    parts.push(rebuild(simpler));
+                }
+            }
+        }
+        BnfPart::Repeat(child) => {
+            parts.push(*child.clone());
+            for child in smaller(child) {
+                parts.push(BnfPart::Repeat(Box::new(child)));
+            }
+        }
+        _ => {}
+    }
+    parts
+}
+
+/// Shrinks rules and a word for which `fails` holds, as long as it still does
+fn shrink(
+    mut rules: Vec<BnfRule>,
+    mut word: String,
+    fails: impl Fn(&[BnfRule], &str) -> bool,
+) -> (Vec<BnfRule>, String) {
+    loop {
+        let mut candidates = Vec::new();
+        for i in 0..word.len() {
+            let mut shorter = word.clone();
+            shorter.remove(i);
+            candidates.push((rules.clone(), shorter));
+        }
+        for i in 0..rules.len() {
+            if rules.len() > 1 {
+                let mut fewer = rules.clone();
+                fewer.remove(i);
+                candidates.push((fewer, word.clone()));
+            }
+            for def in smaller(&rules[i].def) {
+                let mut simpler = rules.clone();
+                simpler[i].def = def;
+                candidates.push((simpler, word.clone()));
+            }
+        }
+        match candidates.into_iter().find(|(r, w)| fails(r, w)) {
+            Some((r, w)) => {
+                rules = r;
+                word = w;
+            }
+            None => return (rules, word),
+        }
+    }
+}
+
+#[test]
+fn matcher() {
+    let rules = super::from_source("<s> <= <s> a | b {<t>}\n<t> <= ab | ()").unwrap();
+    for (word, accepted) in [("b", true), ("baa", true), ("babab", true), ("ba b", false)] {
+        assert_eq!(Matcher::accepts(&rules, word), accepted, "{}", word);
+    }
+    assert!(!Matcher::accepts(&rules, "a"));
+}
+
+#[test]
+fn shrinking() {
+    // pretend that the normal form loses every word with two b's
+    let rules = super::from_source("<s> <= {a | b | <t>}\n<t> <= ab [a]").unwrap();
+    let word = "aabab".to_string();
+    let fails = |rules: &[BnfRule], word: &str| {
+        Matcher::accepts(rules, word) && word.matches('b').count() >= 2
+    };
+    assert!(fails(&rules, &word));
+    let (rules, word) = shrink(rules, word, fails);
+    assert_eq!(word, "bb");
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].to_string(), "<s> <= {b}");
+}
+
+#[test]
+fn normal_form_agrees() {
+    let mut rng = rand::thread_rng();
+    let words = words("ab", 5);
+    for _ in 0..300 {
+        let rules = random_rules(&mut rng);
+        if let Some(word) = words.iter().find(|w| disagree(&rules, w)) {
+            let (rules, word) = shrink(rules, word.clone(), disagree);
+            let verdict = if Matcher::accepts(&rules, &word) {
+                "rejects"
+            } else {
+                "accepts"
+            };
+            let rules = rules.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+            panic!(
+                "the normal form {} {:?}, unlike the rules\n{}",
+                verdict,
+                word,
+                rules.join("\n")
+            );
+        }
+    }
+}
@@ -1,10 +1,12 @@
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BnfRule {
     pub name: String,
     pub def: BnfPart,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BnfPart {
     Empty,
     Literal(String),
@@ -22,14 +24,243 @@ impl BnfPart {
     }
 }
 
+#[cfg(test)]
+pub(crate) mod differential;
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+pub(crate) mod words;
+
+use crate::error::{Error, Result};
 use crate::grammar::*;
+use crate::{debugln, lex, parse, testfile};
 use std::collections::*;
+use std::fmt::{self, Display, Formatter};
+
+/// characters that have to be escaped in literals and rule names
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if "<>()[]{}|\\ \t\r\n".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl Display for BnfRule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<{}> <= {}", escape(&self.name), self.def)
+    }
+}
+
+/// Formats the part in ebnf syntax, such that parsing it again results in an equivalent part
+impl Display for BnfPart {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use BnfPart::*;
+        match self {
+            Empty => write!(f, "()"),
+            Literal(s) if s.is_empty() => write!(f, "()"),
+            Literal(s) => write!(f, "{}", escape(s)),
+            Rule(name) => write!(f, "<{}>", escape(name)),
+            Repeat(part) => write!(f, "{{{}}}", part),
+            Choice(parts) => match &parts[..] {
+                [part, Empty] => write!(f, "[{}]", part),
+                _ => {
+                    for (i, part) in parts.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, " | ")?;
+                        }
+                        match part {
+                            Choice(_) => write!(f, "({})", part)?,
+                            _ => write!(f, "{}", part)?,
+                        }
+                    }
+                    Ok(())
+                }
+            },
+            Concat(parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    match part {
+                        Choice(parts) if parts.len() != 2 || parts[1] != Empty => {
+                            write!(f, "({})", part)?
+                        }
+                        _ => write!(f, "{}", part)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// parses the ebnf rules in `source`, skipping test directives
+///
+/// The directives aren't evaluated here, but malformed ones are still reported, so that a typo
+/// doesn't silently turn a test into nothing.
+pub fn from_source(source: &str) -> Result<Vec<BnfRule>> {
+    let ebnf = testfile::strip_directives(source);
+    let rules = parse::parse(&lex::lex(&ebnf)).map_err(Error::Syntax)?;
+    testfile::directives(source).map_err(Error::Directives)?;
+    debugln!("{:?}\n", rules);
+    Ok(rules)
+}
+
+/// returns the name of the rule that should be used as start
+///
+/// This is `start` if given, otherwise the first rule.
+pub fn root<'a>(rules: &'a [BnfRule], start: Option<&'a str>) -> Result<&'a str> {
+    let start = match (start, rules.first()) {
+        (Some(start), _) => start,
+        (None, Some(first)) => return Ok(&first.name),
+        (None, None) => return Err(Error::NoRules),
+    };
+    if rules.iter().any(|r| r.name == start) {
+        return Ok(start);
+    }
+
+    let mut available = Vec::new();
+    for rule in rules.iter() {
+        if !available.contains(&rule.name) {
+            available.push(rule.name.clone());
+        }
+    }
+    Err(Error::UnknownRule {
+        name: start.to_string(),
+        available,
+    })
+}
 
 impl BnfPart {
-    pub fn simplify(&mut self) {}
+    /// whether the part only produces the empty word
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Empty => true,
+            Self::Literal(s) => s.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Rewrites the part into a simpler one that produces the same words
+    ///
+    /// Nested choices and concatenations are flattened, adjacent literals are merged, empty
+    /// parts are removed from concatenations and duplicate alternatives from choices.
+    /// Nested repetitions and optional parts collapse, e.g. `{{x}}`, `[{x}]` and `{[x]}` all
+    /// become `{x}`, and `[[x]]` becomes `[x]`.
+    pub fn simplify(&mut self) {
+        use BnfPart::*;
+        match self {
+            Empty | Rule(_) => {}
+            Literal(s) => {
+                if s.is_empty() {
+                    *self = Empty;
+                }
+            }
+            Concat(parts) => {
+                let mut flat: Vec<BnfPart> = Vec::new();
+                for mut part in std::mem::take(parts) {
+                    part.simplify();
+                    let inner = match part {
+                        Concat(inner) => inner,
+                        part => vec![part],
+                    };
+                    for part in inner {
+                        match part {
+                            part if part.is_empty() => {}
+                            Literal(s) => match flat.last_mut() {
+                                Some(Literal(last)) => last.push_str(&s),
+                                _ => flat.push(Literal(s)),
+                            },
+                            part => flat.push(part),
+                        }
+                    }
+                }
+                *self = match flat.len() {
+                    0 => Empty,
+                    1 => flat.remove(0),
+                    _ => Concat(flat),
+                };
+            }
+            Choice(parts) => {
+                let mut flat: Vec<BnfPart> = Vec::new();
+                for mut part in std::mem::take(parts) {
+                    part.simplify();
+                    let alternatives = match part {
+                        Choice(inner) => inner,
+                        part => vec![part],
+                    };
+                    for alt in alternatives {
+                        if !flat.contains(&alt) {
+                            flat.push(alt);
+                        }
+                    }
+                }
+                // a repetition already produces the empty word, otherwise it goes last so the
+                // choice reads as an optional part
+                if flat.contains(&Empty) {
+                    flat.retain(|p| *p != Empty);
+                    if !flat.iter().any(|p| matches!(p, Repeat(_))) {
+                        flat.push(Empty);
+                    }
+                }
+                *self = match flat.len() {
+                    1 => flat.remove(0),
+                    _ => Choice(flat),
+                };
+            }
+            Repeat(inner) => {
+                inner.simplify();
+                // the empty word can be left out of a repetition, and repetitions within it
+                // are repeated anyway
+                let alternatives = match std::mem::replace(&mut **inner, Empty) {
+                    Choice(parts) => parts,
+                    part => vec![part],
+                };
+                let mut flat: Vec<BnfPart> = Vec::new();
+                for alt in alternatives {
+                    let alts = match alt {
+                        Repeat(part) => match *part {
+                            Choice(parts) => parts,
+                            part => vec![part],
+                        },
+                        alt => vec![alt],
+                    };
+                    for alt in alts {
+                        if alt != Empty && !flat.contains(&alt) {
+                            flat.push(alt);
+                        }
+                    }
+                }
+                let part = match flat.len() {
+                    0 => Empty,
+                    1 => flat.remove(0),
+                    _ => Choice(flat),
+                };
+                *self = match part {
+                    Empty => Empty,
+                    part => Repeat(Box::new(part)),
+                };
+            }
+        }
+    }
 }
 
 pub fn to_grammar(rules: &[BnfRule], root: &str) -> Grammar {
+    let (mut grammar, lookup) = nonterminals(rules);
+    grammar.start = match lookup.get(&root.to_string()) {
+        Some(&root) => root,
+        None => grammar.add_rule(vec![]),
+    };
+    grammar
+}
+
+/// converts the rules into a grammar without a meaningful start, together with the nonterminal
+/// of every rule name
+pub(crate) fn nonterminals(rules: &[BnfRule]) -> (Grammar, HashMap<&String, NonTerminal>) {
     fn convert<'a>(
         part: &'a BnfPart,
         lookup: &mut HashMap<&'a String, NonTerminal>,
@@ -78,22 +309,14 @@ pub fn to_grammar(rules: &[BnfRule], root: &str) -> Grammar {
     // rule name to nonterminal
     let mut lookup = HashMap::new();
 
+    // every name gets a rule of its own, as it may be defined more than once
     for rule in rules.iter() {
         let nt = convert(&rule.def, &mut lookup, &mut grammar);
-        if let Some(&def) = lookup.get(&rule.name) {
-            grammar.rules[def].push(vec![Token::NT(nt)]);
-        } else {
-            lookup.insert(&rule.name, nt);
-        }
-    }
-
-    let root = root.to_string();
-
-    if let Some(&root) = lookup.get(&root) {
-        grammar.start = root;
-    } else {
-        grammar.start = grammar.add_rule(vec![]);
+        let def = *lookup
+            .entry(&rule.name)
+            .or_insert_with(|| grammar.add_rule(vec![]));
+        grammar.rules[def].push(vec![Token::NT(nt)]);
     }
 
-    grammar
+    (grammar, lookup)
 }
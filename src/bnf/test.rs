@@ -0,0 +1,119 @@
+use super::*;
+use crate::chomsky;
+use crate::compare::Comparison;
+
+fn simplified(source: &str) -> String {
+    let mut rules = from_source(source).unwrap();
+    rules[0].def.simplify();
+    rules[0].def.to_string()
+}
+
+#[test]
+fn flatten() {
+    assert_eq!(simplified("<s> <= a (b c) | (d | e)"), "abc | d | e");
+    assert_eq!(simplified("<s> <= a (b (c | d))"), "ab (c | d)");
+}
+
+#[test]
+fn empty_parts() {
+    assert_eq!(simplified("<s> <= a () b"), "ab");
+    assert_eq!(simplified("<s> <= () ()"), "()");
+    assert_eq!(simplified("<s> <= () | a"), "[a]");
+    assert_eq!(simplified("<s> <= {()}"), "()");
+}
+
+#[test]
+fn duplicates() {
+    assert_eq!(simplified("<s> <= a | b | a"), "a | b");
+    assert_eq!(simplified("<s> <= [a] | b | ()"), "a | b | ()");
+}
+
+#[test]
+fn nested_repetitions() {
+    assert_eq!(simplified("<s> <= {{x}}"), "{x}");
+    assert_eq!(simplified("<s> <= [{x}]"), "{x}");
+    assert_eq!(simplified("<s> <= [[x]]"), "[x]");
+    assert_eq!(simplified("<s> <= {[x]}"), "{x}");
+    assert_eq!(simplified("<s> <= {{a} | b | a}"), "{a | b}");
+}
+
+/// compares the grammars built with and without simplifying the rules first
+#[test]
+fn same_words() {
+    let sources = [
+        "<s> <= {{a} | [b c]} (d | (e | ())) {[f]}",
+        "<s> <= [[<t>]] <t> | () <t> ()\n<t> <= {x} y | [{z}]",
+        "<s> <= (a | (b | {c})) | [d (e f)]",
+    ];
+    for source in sources.iter() {
+        let rules = from_source(source).unwrap();
+        let mut plain = to_grammar(&rules, "s");
+        plain.simplify();
+        plain.normalize();
+        let plain = chomsky::Grammar::from_normalized(&plain).unwrap();
+        let simplified = chomsky::Grammar::from_rules(&rules, None).unwrap();
+        assert!(simplified.rules.len() <= plain.rules.len());
+        let comparison = Comparison::from_grammars(plain, simplified, 500);
+        assert!(comparison.first.is_empty(), "{}: {:?}", source, comparison);
+        assert!(comparison.second.is_empty(), "{}: {:?}", source, comparison);
+    }
+}
+
+#[test]
+fn display_roundtrip() {
+    use BnfPart::*;
+    let parts = vec![
+        Literal("a<=b c\\".into()),
+        Rule("my rule".into()),
+        Choice(vec![Literal("a".into()), Empty, Rule("b".into())]),
+        Concat(vec![
+            Choice(vec![Literal("a".into()), Literal("b".into())]),
+            BnfPart::Opt(Rule("c".into())),
+            Repeat(Box::new(Concat(vec![
+                Literal("d".into()),
+                Rule("e".into()),
+            ]))),
+        ]),
+        Choice(vec![
+            Choice(vec![Literal("a".into()), Literal("b".into())]),
+            Literal("c".into()),
+        ]),
+    ];
+    for part in parts {
+        let rule = BnfRule {
+            name: "s".into(),
+            def: part,
+        };
+        assert_eq!(from_source(&rule.to_string()).unwrap(), vec![rule]);
+    }
+}
+
+#[test]
+fn repeated_rule_names() {
+    // the second definition is an alternative to the whole first one, not to its repetition
+    let rules = from_source("<s> <= {a}\n<s> <= b").unwrap();
+    let grammar = chomsky::Grammar::from_rules(&rules, None).unwrap();
+    for word in ["", "a", "aa", "b"] {
+        assert!(grammar.accepts(word), "{}", word);
+    }
+    for word in ["ab", "ba", "bb"] {
+        assert!(!grammar.accepts(word), "{}", word);
+    }
+}
+
+#[test]
+fn empty_sources() {
+    for source in ["", "\n\n", "  \t\n"] {
+        let result = chomsky::Grammar::from_source(source, &Default::default());
+        assert!(matches!(result, Err(Error::NoRules)), "{:?}", source);
+    }
+}
+
+#[test]
+fn malformed_directives() {
+    assert!(from_source("<s> <= a\n@accept <s> a\n  @reject <s> b").is_ok());
+    match from_source("<s> <= a\n@acept <s> a") {
+        Err(Error::Directives(errors)) => assert_eq!(errors[0].line, 2),
+        other => panic!("{:?}", other),
+    }
+}
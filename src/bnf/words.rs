@@ -0,0 +1,26 @@
+/// all words over `alphabet` up to length `max`, shortest first
+pub(crate) fn words(alphabet: &str, max: usize) -> Vec<String> {
+    let mut words = vec![String::new()];
+    let mut last = words.clone();
+    for _ in 0..max {
+        last = last
+            .iter()
+            .flat_map(|w| alphabet.chars().map(move |c| format!("{}{}", w, c)))
+            .collect();
+        words.extend(last.iter().cloned());
+    }
+    words
+}
+
+/// asserts that `actual` gives the same verdict as `expected` on every word, naming `context`
+/// and the word otherwise
+pub(crate) fn assert_agree(
+    words: &[String],
+    expected: impl Fn(&str) -> bool,
+    actual: impl Fn(&str) -> bool,
+    context: &str,
+) {
+    for word in words {
+        assert_eq!(actual(word), expected(word), "{}: '{}'", context, word);
+    }
+}
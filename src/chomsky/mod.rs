@@ -1,7 +1,33 @@
-use crate::grammar;
+use crate::bnf::{self, BnfRule};
+use crate::error::Error;
+use crate::{debugln, grammar};
 pub use grammar::{NonTerminal, Terminal, TerminalRef};
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+#[cfg(test)]
+mod test;
+
+/// Options for building a grammar from ebnf rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// name of the start rule, the first rule is used if it is `None`
+    pub start: Option<String>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn start(mut self, start: &str) -> Self {
+        self.start = Some(start.to_string());
+        self
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Definition {
     /// Terminal, i.e. a sequence of characters
     Term(Terminal),
@@ -11,8 +37,39 @@ pub enum Definition {
 
 pub type Rule = Vec<Definition>;
 
+/// Number of rules and definitions of a grammar in normal form
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Size {
+    pub rules: usize,
+    /// definitions that are terminals
+    pub terms: usize,
+    /// definitions that are products of two nonterminals
+    pub products: usize,
+}
+
+impl Size {
+    /// total number of definitions
+    pub fn definitions(&self) -> usize {
+        self.terms + self.products
+    }
+}
+
+impl Display for Size {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} rules with {} definitions ({} terminals, {} products)",
+            self.rules,
+            self.definitions(),
+            self.terms,
+            self.products
+        )
+    }
+}
+
 /// A Grammar in the Chomsky Normal Form
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grammar {
     /// starting symbol
     pub start: NonTerminal,
@@ -23,6 +80,42 @@ pub struct Grammar {
 }
 
 impl Grammar {
+    /// reads a rules file and builds the grammar of its start rule
+    ///
+    /// With the `serde` feature, files ending in `.json` are read as compiled grammars.
+    pub fn from_file(path: &Path, options: &Options) -> Result<Self, Error> {
+        let source = std::fs::read_to_string(path).map_err(|error| Error::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        #[cfg(feature = "serde")]
+        if crate::compiled::is_compiled(path) {
+            let compiled = crate::compiled::Compiled::from_json(&source)?;
+            return compiled.grammar(options.start.as_deref());
+        }
+        Self::from_source(&source, options)
+    }
+
+    /// parses ebnf rules and builds the grammar of the start rule
+    pub fn from_source(source: &str, options: &Options) -> Result<Self, Error> {
+        let rules = bnf::from_source(source)?;
+        Self::from_rules(&rules, options.start.as_deref())
+    }
+
+    /// builds the grammar of the rule named `start`, or the first rule if it is `None`
+    pub fn from_rules(rules: &[BnfRule], start: Option<&str>) -> Result<Self, Error> {
+        let root = bnf::root(rules, start)?;
+        let mut rules = rules.to_vec();
+        for rule in rules.iter_mut() {
+            rule.def.simplify();
+        }
+        let mut grammar = bnf::to_grammar(&rules, root);
+        grammar.simplify();
+        grammar.normalize();
+        debugln!("{}", grammar);
+        Self::from_normalized(&grammar).map_err(Error::Normalization)
+    }
+
     /// returns `Ok(grammar)` if the given argument is in normal form
     ///
     /// otherwise it returns `Err(string)` with a normal form violation
@@ -72,10 +165,34 @@ impl Grammar {
         })
     }
 
+    /// counts the rules and definitions of the grammar
+    pub fn size(&self) -> Size {
+        let mut size = Size {
+            rules: self.rules.len(),
+            ..Size::default()
+        };
+        for def in self.rules.iter().flatten() {
+            match def {
+                Definition::Term(_) => size.terms += 1,
+                Definition::Product(_) => size.products += 1,
+            }
+        }
+        size
+    }
+
     /// Checks if a word is accepted by this grammar
     ///
-    /// It uses the simple [CYK algorithm](https://en.wikipedia.org/wiki/CYK_algorithm)
+    /// It uses the [CYK algorithm](https://en.wikipedia.org/wiki/CYK_algorithm) on sets of
+    /// nonterminals, see [`Cyk`](crate::cyk::Cyk). When checking many words, build the
+    /// recognizer once instead.
     pub fn accepts(&self, word: &str) -> bool {
+        crate::cyk::Cyk::new(self).accepts(word)
+    }
+
+    /// Checks if a word is accepted by this grammar, using the simple CYK algorithm
+    ///
+    /// This is much slower than [`accepts`](Self::accepts) and only kept as reference.
+    pub fn accepts_naive(&self, word: &str) -> bool {
         let chars = word.chars().collect::<Vec<_>>();
         #[allow(non_snake_case)]
         let N = chars.len();
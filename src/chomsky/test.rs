@@ -0,0 +1,98 @@
+use super::*;
+use std::path::PathBuf;
+
+#[test]
+fn from_file() {
+    let options = Options::new().start("scream");
+    let grammar = Grammar::from_file(Path::new("rules/scream"), &options).unwrap();
+    assert!(grammar.accepts("aAaA"));
+    assert!(!grammar.accepts("foo"));
+}
+
+#[test]
+fn io_error() {
+    let path = PathBuf::from("rules/does-not-exist");
+    match Grammar::from_file(&path, &Options::new()) {
+        Err(err @ Error::Io { .. }) => {
+            assert_eq!(err.to_string(), "failed to read file rules/does-not-exist");
+            assert!(std::error::Error::source(&err).is_some());
+            match err {
+                Error::Io { path: p, error } => {
+                    assert_eq!(p, path);
+                    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+                }
+                _ => unreachable!(),
+            }
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn syntax_error() {
+    match Grammar::from_source("<s> <= (a\n<t> <= b\n<u> a", &Options::new()) {
+        Err(err @ Error::Syntax(_)) => {
+            assert_eq!(err.to_string(), "2 syntax errors");
+            let Error::Syntax(errors) = err else {
+                unreachable!()
+            };
+            let positions = errors
+                .iter()
+                .map(|e| e.position("<s> <= (a\n<t> <= b\n<u> a"))
+                .collect::<Vec<_>>();
+            assert_eq!(positions, vec![Some((1, 10)), Some((3, 5))]);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn no_rules() {
+    let result = Grammar::from_source("\n", &Options::new());
+    assert!(matches!(result, Err(Error::NoRules)), "{:?}", result);
+}
+
+#[test]
+fn unknown_start() {
+    let source = "<s> <= <t>\n<t> <= a\n<s> <= b";
+    match Grammar::from_source(source, &Options::new().start("u")) {
+        Err(err @ Error::UnknownRule { .. }) => {
+            assert_eq!(
+                err.to_string(),
+                "there is no rule named <u>, available rules are: <s>, <t>"
+            );
+            let Error::UnknownRule { name, available } = err else {
+                unreachable!()
+            };
+            assert_eq!(name, "u");
+            assert_eq!(available, vec!["s", "t"]);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(Grammar::from_source(source, &Options::new().start("t")).is_ok());
+}
+
+#[test]
+fn normalization_error() {
+    // the pipeline always normalizes, so this can only come from a grammar built by hand
+    let unit = grammar::Grammar {
+        start: 0,
+        rules: vec![vec![vec![grammar::Token::NT(1)]], vec![vec![]]],
+    };
+    let reason = Grammar::from_normalized(&unit).unwrap_err();
+    assert_eq!(reason, "Unit productions aren't allowed.");
+    let err = Error::Normalization(reason);
+    assert_eq!(
+        err.to_string(),
+        "internal error: failed to normalize grammar"
+    );
+
+    let empty = grammar::Grammar {
+        start: 0,
+        rules: vec![vec![vec![grammar::Token::T(vec!['a'])]], vec![vec![]]],
+    };
+    assert_eq!(
+        Grammar::from_normalized(&empty).unwrap_err(),
+        "only the starting rule may produce the empty symbol."
+    );
+}
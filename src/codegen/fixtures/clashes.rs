@@ -0,0 +1,112 @@
+//! Recognizer for the grammar in `src/codegen/fixtures/clashes`, generated by parsley
+//!
+//! The grammar isn't LL(1), so this uses the CYK algorithm on the normalized grammar.
+//! Don't edit this file, regenerate it with `parsley codegen` instead.
+
+/// Checks whether `word` is accepted by the top-level rule `<s>`
+pub fn accepts(word: &str) -> bool {
+    s(word)
+}
+
+/// Checks whether `word` is accepted by the rule `<s>`
+pub fn s(word: &str) -> bool {
+    cyk(&GRAMMAR_0, word)
+}
+
+/// Checks whether `word` is accepted by the rule `<cyk>`
+pub fn cyk_2(word: &str) -> bool {
+    cyk(&GRAMMAR_1, word)
+}
+
+/// Checks whether `word` is accepted by the rule `<GRAMMAR_0>`
+#[allow(non_snake_case)]
+pub fn GRAMMAR_0_2(word: &str) -> bool {
+    cyk(&GRAMMAR_2, word)
+}
+
+/// Checks whether `word` is accepted by the rule `<Some>`
+#[allow(non_snake_case)]
+pub fn Some_2(word: &str) -> bool {
+    cyk(&GRAMMAR_3, word)
+}
+
+static GRAMMAR_0: Grammar = Grammar {
+    start: 0,
+    null: false,
+    rules: 1,
+    terms: &[(0, "a"), (0, "ab"), (0, "b"), (0, "c"), (0, "d")],
+    products: &[],
+};
+
+static GRAMMAR_1: Grammar = Grammar {
+    start: 0,
+    null: false,
+    rules: 1,
+    terms: &[(0, "a"), (0, "ab")],
+    products: &[],
+};
+
+static GRAMMAR_2: Grammar = Grammar {
+    start: 0,
+    null: false,
+    rules: 1,
+    terms: &[(0, "c")],
+    products: &[],
+};
+
+static GRAMMAR_3: Grammar = Grammar {
+    start: 0,
+    null: false,
+    rules: 1,
+    terms: &[(0, "d")],
+    products: &[],
+};
+
+/// A grammar in Chomsky normal form
+struct Grammar {
+    start: usize,
+    /// whether the empty word is accepted
+    null: bool,
+    rules: usize,
+    /// `(a, t)` stands for the production `a -> t`
+    terms: &'static [(usize, &'static str)],
+    /// `(a, b, c)` stands for the production `a -> b c`
+    products: &'static [(usize, usize, usize)],
+}
+
+fn cyk(grammar: &Grammar, word: &str) -> bool {
+    let chars = word.chars().collect::<Vec<_>>();
+    let n = chars.len();
+    if n == 0 {
+        return grammar.null;
+    }
+
+    // whether rule `r` produces the word from `start` to `end`
+    let idx = |start: usize, end: usize, r: usize| (start * (n + 1) + end) * grammar.rules + r;
+    let mut p = vec![false; n * (n + 1) * grammar.rules];
+
+    for &(r, term) in grammar.terms {
+        let term = term.chars().collect::<Vec<_>>();
+        for start in 0..n {
+            let end = start + term.len();
+            if end <= n && chars[start..end] == term[..] {
+                p[idx(start, end, r)] = true;
+            }
+        }
+    }
+
+    for len in 2..=n {
+        for start in 0..=n - len {
+            let end = start + len;
+            for pivot in start + 1..end {
+                for &(a, b, c) in grammar.products {
+                    if p[idx(start, pivot, b)] && p[idx(pivot, end, c)] {
+                        p[idx(start, end, a)] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    p[idx(0, n, grammar.start)]
+}
@@ -0,0 +1,108 @@
+//! Recognizer for the grammar in `src/codegen/fixtures/lists`, generated by parsley
+//!
+//! The grammar is LL(1), so this is a recursive-descent parser.
+//! Don't edit this file, regenerate it with `parsley codegen` instead.
+
+/// Checks whether `word` is accepted by the top-level rule `<list>`
+pub fn accepts(word: &str) -> bool {
+    list(word)
+}
+
+/// Checks whether `word` is accepted by the rule `<list>`
+pub fn list(word: &str) -> bool {
+    let mut parser = Parser::new(word);
+    parser.rule_0() && parser.finished()
+}
+
+/// Checks whether `word` is accepted by the rule `<item>`
+pub fn item(word: &str) -> bool {
+    let mut parser = Parser::new(word);
+    parser.rule_1() && parser.finished()
+}
+
+/// Checks whether `word` is accepted by the rule `<number>`
+pub fn number(word: &str) -> bool {
+    let mut parser = Parser::new(word);
+    parser.rule_2() && parser.finished()
+}
+
+struct Parser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(word: &str) -> Self {
+        Self {
+            input: word.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn finished(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn literal(&mut self, literal: &str) -> bool {
+        for c in literal.chars() {
+            if self.peek() != Some(c) {
+                return false;
+            }
+            self.pos += 1;
+        }
+        true
+    }
+
+    /// `<list>`
+    fn rule_0(&mut self) -> bool {
+        self.literal("[")
+            && match self.peek() {
+                Some('0') | Some('1') | Some('[') => self.rule_1()
+                    && {
+                        while matches!(self.peek(), Some(',')) {
+                            if !(self.literal(",")
+                                    && self.rule_1()) {
+                                return false;
+                            }
+                        }
+                        true
+                    },
+                _ => true,
+            }
+            && self.literal("]")
+    }
+
+    /// `<item>`
+    fn rule_1(&mut self) -> bool {
+        match self.peek() {
+            Some('0') | Some('1') => self.rule_2(),
+            Some('[') => self.rule_0(),
+            _ => false,
+        }
+    }
+
+    /// `<number>`
+    fn rule_2(&mut self) -> bool {
+        (match self.peek() {
+                Some('0') => self.literal("0"),
+                Some('1') => self.literal("1"),
+                _ => false,
+            })
+            && {
+                while matches!(self.peek(), Some('0') | Some('1')) {
+                    if !match self.peek() {
+                        Some('0') => self.literal("0"),
+                        Some('1') => self.literal("1"),
+                        _ => false,
+                    } {
+                        return false;
+                    }
+                }
+                true
+            }
+    }
+}
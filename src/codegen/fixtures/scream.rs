@@ -0,0 +1,63 @@
+//! Recognizer for the grammar in `rules/scream`, generated by parsley
+//!
+//! The grammar is LL(1), so this is a recursive-descent parser.
+//! Don't edit this file, regenerate it with `parsley codegen` instead.
+
+/// Checks whether `word` is accepted by the top-level rule `<scream>`
+pub fn accepts(word: &str) -> bool {
+    scream(word)
+}
+
+/// Checks whether `word` is accepted by the rule `<scream>`
+pub fn scream(word: &str) -> bool {
+    let mut parser = Parser::new(word);
+    parser.rule_0() && parser.finished()
+}
+
+struct Parser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(word: &str) -> Self {
+        Self {
+            input: word.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn finished(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn literal(&mut self, literal: &str) -> bool {
+        for c in literal.chars() {
+            if self.peek() != Some(c) {
+                return false;
+            }
+            self.pos += 1;
+        }
+        true
+    }
+
+    /// `<scream>`
+    fn rule_0(&mut self) -> bool {
+        {
+            while matches!(self.peek(), Some('A') | Some('a')) {
+                if !match self.peek() {
+                    Some('a') => self.literal("a"),
+                    Some('A') => self.literal("A"),
+                    _ => false,
+                } {
+                    return false;
+                }
+            }
+            true
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use crate::bnf::{BnfPart, BnfRule};
+use crate::chomsky::{self, Definition};
+use crate::error::Error;
+use crate::ll1::Analysis;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+
+#[cfg(test)]
+mod test;
+
+/// Rust keywords, which can't be used as function names
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// items the generated code defines or uses besides the functions of the rules
+const RESERVED: &[&str] = &["accepts", "cyk", "Grammar", "Parser", "Some"];
+
+/// turns rule names into distinct rust identifiers
+///
+/// Characters that can't be part of an identifier are replaced by `_`, and names of other items
+/// in the generated code, including the static `GRAMMAR_<n>` of every rule, are avoided.
+fn identifiers(names: &[String]) -> Vec<String> {
+    let mut used = RESERVED
+        .iter()
+        .map(|s| s.to_string())
+        .chain((0..names.len()).map(|i| format!("GRAMMAR_{}", i)))
+        .collect::<HashSet<_>>();
+    names
+        .iter()
+        .map(|name| {
+            let mut ident = name
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect::<String>();
+            if ident.is_empty() || ident.starts_with(|c: char| c.is_numeric()) {
+                ident.insert(0, '_');
+            }
+            if ident == "_" || KEYWORDS.contains(&ident.as_str()) {
+                ident.push('_');
+            }
+            let mut unique = ident.clone();
+            let mut n = 1;
+            while used.contains(&unique) {
+                n += 1;
+                unique = format!("{}_{}", ident, n);
+            }
+            used.insert(unique.clone());
+            unique
+        })
+        .collect()
+}
+
+/// Generates a rust module without any dependencies that recognizes the words of a grammar
+///
+/// There is a function `fn(&str) -> bool` for every rule, and `accepts` for the rule `start`.
+/// If the grammar is LL(1), the module contains a recursive-descent parser, otherwise it
+/// contains the grammar in normal form for every rule, together with the CYK algorithm.
+pub fn rust(rules: &[BnfRule], start: &str, file: &str) -> Result<String, Error> {
+    let start = crate::bnf::root(rules, Some(start))?;
+    let analysis = Analysis::new(rules, start);
+    let idents = identifiers(&analysis.names);
+    let ll1 = analysis.is_ll1();
+
+    let mut out = String::new();
+    let w = &mut out;
+    writeln!(
+        w,
+        "//! Recognizer for the grammar in `{}`, generated by parsley",
+        file
+    )
+    .unwrap();
+    writeln!(w, "//!").unwrap();
+    if ll1 {
+        writeln!(
+            w,
+            "//! The grammar is LL(1), so this is a recursive-descent parser."
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            w,
+            "//! The grammar isn't LL(1), so this uses the CYK algorithm on the normalized grammar."
+        )
+        .unwrap();
+    }
+    writeln!(
+        w,
+        "//! Don't edit this file, regenerate it with `parsley codegen` instead."
+    )
+    .unwrap();
+
+    let start_idx = analysis.rule(start).expect("the start rule exists");
+    writeln!(w).unwrap();
+    writeln!(
+        w,
+        "/// Checks whether `word` is accepted by the top-level rule `<{}>`",
+        start
+    )
+    .unwrap();
+    writeln!(w, "pub fn accepts(word: &str) -> bool {{").unwrap();
+    writeln!(w, "    {}(word)", idents[start_idx]).unwrap();
+    writeln!(w, "}}").unwrap();
+
+    for (name, ident) in analysis.names.iter().zip(idents.iter()) {
+        writeln!(w).unwrap();
+        writeln!(
+            w,
+            "/// Checks whether `word` is accepted by the rule `<{}>`",
+            name
+        )
+        .unwrap();
+        if ident.chars().any(char::is_uppercase) {
+            writeln!(w, "#[allow(non_snake_case)]").unwrap();
+        }
+        writeln!(w, "pub fn {}(word: &str) -> bool {{", ident).unwrap();
+        if ll1 {
+            let idx = analysis.rule(name).unwrap();
+            writeln!(w, "    let mut parser = Parser::new(word);").unwrap();
+            writeln!(w, "    parser.rule_{}() && parser.finished()", idx).unwrap();
+        } else {
+            let idx = analysis.rule(name).unwrap();
+            writeln!(w, "    cyk(&GRAMMAR_{}, word)", idx).unwrap();
+        }
+        writeln!(w, "}}").unwrap();
+    }
+
+    if ll1 {
+        recursive_descent(&analysis, w);
+    } else {
+        for (idx, name) in analysis.names.iter().enumerate() {
+            let grammar = chomsky::Grammar::from_rules(rules, Some(name))?;
+            cyk_table(&format!("GRAMMAR_{}", idx), &grammar, w);
+        }
+        w.push_str(CYK);
+    }
+    Ok(out)
+}
+
+const PARSER: &str = r#"
+struct Parser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(word: &str) -> Self {
+        Self {
+            input: word.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn finished(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn literal(&mut self, literal: &str) -> bool {
+        for c in literal.chars() {
+            if self.peek() != Some(c) {
+                return false;
+            }
+            self.pos += 1;
+        }
+        true
+    }
+"#;
+
+fn recursive_descent(analysis: &Analysis, w: &mut String) {
+    w.push_str(PARSER);
+    for (idx, def) in analysis.defs.iter().enumerate() {
+        writeln!(w).unwrap();
+        writeln!(w, "    /// `<{}>`", analysis.names[idx]).unwrap();
+        writeln!(w, "    fn rule_{}(&mut self) -> bool {{", idx).unwrap();
+        writeln!(w, "        {}", expression(analysis, def, 2)).unwrap();
+        writeln!(w, "    }}").unwrap();
+    }
+    writeln!(w, "}}").unwrap();
+}
+
+/// match pattern for the given lookahead symbols
+fn pattern(symbols: &BTreeSet<char>) -> String {
+    symbols
+        .iter()
+        .map(|c| format!("Some({:?})", c))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// rust expression that parses `part` and evaluates to whether that succeeded
+fn expression(analysis: &Analysis, part: &BnfPart, indent: usize) -> String {
+    use BnfPart::*;
+    let pad = "    ".repeat(indent);
+    match part {
+        Empty => String::from("true"),
+        Literal(s) => format!("self.literal({:?})", s),
+        Rule(name) => match analysis.rule(name) {
+            Some(idx) => format!("self.rule_{}()", idx),
+            None => format!("false /* <{}> is undefined */", name),
+        },
+        Concat(parts) => {
+            let parts = parts
+                .iter()
+                .enumerate()
+                .map(|(i, p)| match p {
+                    // a leading block or match would be parsed as a statement
+                    Choice(_) | Repeat(_) if i == 0 => {
+                        format!("({})", expression(analysis, p, indent + 1))
+                    }
+                    _ => expression(analysis, p, indent + 1),
+                })
+                .collect::<Vec<_>>();
+            parts.join(&format!("\n{}    && ", pad))
+        }
+        Choice(parts) => {
+            let mut out = String::from("match self.peek() {\n");
+            for p in parts.iter() {
+                let first = analysis.first_part(p);
+                if !first.is_empty() {
+                    writeln!(
+                        out,
+                        "{}    {} => {},",
+                        pad,
+                        pattern(&first),
+                        expression(analysis, p, indent + 1)
+                    )
+                    .unwrap();
+                }
+            }
+            let fallback = match parts.iter().find(|p| analysis.nullable_part(p)) {
+                Some(p) => expression(analysis, p, indent + 1),
+                None => String::from("false"),
+            };
+            writeln!(out, "{}    _ => {},", pad, fallback).unwrap();
+            write!(out, "{}}}", pad).unwrap();
+            out
+        }
+        Repeat(inner) => {
+            let first = analysis.first_part(inner);
+            if first.is_empty() {
+                return String::from("true");
+            }
+            format!(
+                "{{\n{pad}    while matches!(self.peek(), {pattern}) {{\n{pad}        if !{expr} {{\n{pad}            return false;\n{pad}        }}\n{pad}    }}\n{pad}    true\n{pad}}}",
+                pad = pad,
+                pattern = pattern(&first),
+                expr = match **inner {
+                    Concat(_) => format!("({})", expression(analysis, inner, indent + 3)),
+                    _ => expression(analysis, inner, indent + 2),
+                },
+            )
+        }
+    }
+}
+
+fn cyk_table(name: &str, grammar: &chomsky::Grammar, w: &mut String) {
+    let mut terms = Vec::new();
+    let mut products = Vec::new();
+    for (r, rule) in grammar.rules.iter().enumerate() {
+        for def in rule.iter() {
+            match def {
+                Definition::Term(t) => {
+                    terms.push(format!("({}, {:?})", r, t.iter().collect::<String>()))
+                }
+                Definition::Product([a, b]) => products.push(format!("({}, {}, {})", r, a, b)),
+            }
+        }
+    }
+    writeln!(w).unwrap();
+    writeln!(w, "static {}: Grammar = Grammar {{", name).unwrap();
+    writeln!(w, "    start: {},", grammar.start).unwrap();
+    writeln!(w, "    null: {},", grammar.null).unwrap();
+    writeln!(w, "    rules: {},", grammar.rules.len()).unwrap();
+    writeln!(w, "    terms: &[{}],", terms.join(", ")).unwrap();
+    writeln!(w, "    products: &[{}],", products.join(", ")).unwrap();
+    writeln!(w, "}};").unwrap();
+}
+
+const CYK: &str = r#"
+/// A grammar in Chomsky normal form
+struct Grammar {
+    start: usize,
+    /// whether the empty word is accepted
+    null: bool,
+    rules: usize,
+    /// `(a, t)` stands for the production `a -> t`
+    terms: &'static [(usize, &'static str)],
+    /// `(a, b, c)` stands for the production `a -> b c`
+    products: &'static [(usize, usize, usize)],
+}
+
+fn cyk(grammar: &Grammar, word: &str) -> bool {
+    let chars = word.chars().collect::<Vec<_>>();
+    let n = chars.len();
+    if n == 0 {
+        return grammar.null;
+    }
+
+    // whether rule `r` produces the word from `start` to `end`
+    let idx = |start: usize, end: usize, r: usize| (start * (n + 1) + end) * grammar.rules + r;
+    let mut p = vec![false; n * (n + 1) * grammar.rules];
+
+    for &(r, term) in grammar.terms {
+        let term = term.chars().collect::<Vec<_>>();
+        for start in 0..n {
+            let end = start + term.len();
+            if end <= n && chars[start..end] == term[..] {
+                p[idx(start, end, r)] = true;
+            }
+        }
+    }
+
+    for len in 2..=n {
+        for start in 0..=n - len {
+            let end = start + len;
+            for pivot in start + 1..end {
+                for &(a, b, c) in grammar.products {
+                    if p[idx(start, pivot, b)] && p[idx(pivot, end, c)] {
+                        p[idx(start, end, a)] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    p[idx(0, n, grammar.start)]
+}
+"#;
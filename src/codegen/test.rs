@@ -0,0 +1,143 @@
+use super::*;
+use crate::bnf::words::{assert_agree, words};
+use crate::chomsky::{Grammar, Options};
+use crate::producer::Producer;
+
+// generated code is compiled as it is, regenerate it with
+// `parsley codegen <rules> -o src/codegen/fixtures/<name>.rs` after changing the generator
+#[rustfmt::skip]
+#[path = "fixtures/scream.rs"]
+mod scream;
+#[rustfmt::skip]
+#[path = "fixtures/lists.rs"]
+mod lists;
+#[rustfmt::skip]
+#[path = "fixtures/binary_div3.rs"]
+mod binary_div3;
+#[rustfmt::skip]
+#[path = "fixtures/clashes.rs"]
+mod clashes;
+
+/// rules file, start rule and generated code of every fixture
+const FIXTURES: &[(&str, &str, &str)] = &[
+    ("rules/scream", "scream", include_str!("fixtures/scream.rs")),
+    (
+        "src/codegen/fixtures/lists",
+        "list",
+        include_str!("fixtures/lists.rs"),
+    ),
+    (
+        "rules/binary-div3",
+        "0",
+        include_str!("fixtures/binary_div3.rs"),
+    ),
+    (
+        "src/codegen/fixtures/clashes",
+        "s",
+        include_str!("fixtures/clashes.rs"),
+    ),
+];
+
+/// checks that `accepts` agrees with the normal form of the rule `start` on all words up to
+/// length `len` over `alphabet`, and on the first words the grammar produces
+fn agrees(accepts: fn(&str) -> bool, file: &str, start: &str, alphabet: &str, len: usize) {
+    let options = Options::new().start(start);
+    let grammar = Grammar::from_file(file.as_ref(), &options).unwrap();
+    let mut words = words(alphabet, len);
+    let produced = Producer::new(grammar.clone()).take(200);
+    words.extend(produced.map(|w| w.into_iter().collect::<String>()));
+    assert_agree(
+        &words,
+        |w| grammar.accepts(w),
+        accepts,
+        &format!("<{}> of {}", start, file),
+    );
+}
+
+#[test]
+fn rule_identifiers() {
+    let names = [
+        "scream",
+        "long-scream",
+        "0",
+        "type",
+        "accepts",
+        "long_scream",
+        "",
+        "cyk",
+        "GRAMMAR_1",
+        "GRAMMAR_9",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<Vec<_>>();
+    assert_eq!(
+        identifiers(&names),
+        vec![
+            "scream",
+            "long_scream",
+            "_0",
+            "type_",
+            "accepts_2",
+            "long_scream_2",
+            "__",
+            "cyk_2",
+            "GRAMMAR_1_2",
+            "GRAMMAR_9_2",
+        ]
+    );
+}
+
+#[test]
+fn fixtures_are_current() {
+    for (file, start, fixture) in FIXTURES {
+        let source = std::fs::read_to_string(file).unwrap();
+        let rules = crate::bnf::from_source(&source).unwrap();
+        assert!(
+            rust(&rules, start, file).unwrap() == *fixture,
+            "the code generated for {} changed, regenerate its fixture",
+            file
+        );
+    }
+}
+
+#[test]
+fn recursive_descent() {
+    assert!(scream::accepts("aAaA"));
+    agrees(scream::accepts, "rules/scream", "scream", "aAb", 5);
+    agrees(scream::scream, "rules/scream", "scream", "aA", 3);
+
+    let lists = "src/codegen/fixtures/lists";
+    assert!(lists::accepts("[10,[],[1,[0]]]"));
+    agrees(lists::accepts, lists, "list", "[],01", 5);
+    agrees(lists::list, lists, "list", "[],1", 3);
+    agrees(lists::item, lists, "item", "[],01", 4);
+    agrees(lists::number, lists, "number", "01,", 4);
+}
+
+#[test]
+fn cyk() {
+    assert!(binary_div3::accepts("1001"));
+    agrees(binary_div3::accepts, "rules/binary-div3", "0", "01", 8);
+    agrees(binary_div3::_0, "rules/binary-div3", "0", "01", 3);
+    agrees(binary_div3::_1, "rules/binary-div3", "1", "01", 8);
+    agrees(binary_div3::_2, "rules/binary-div3", "2", "01", 8);
+    agrees(
+        binary_div3::comment,
+        "rules/binary-div3",
+        "comment",
+        "Tis ",
+        3,
+    );
+}
+
+/// rules named like the helpers of the generated code
+#[test]
+fn clashing_names() {
+    let clashes = "src/codegen/fixtures/clashes";
+    assert!(clashes::accepts("ab") && !clashes::accepts("ba"));
+    agrees(clashes::accepts, clashes, "s", "abcd", 3);
+    agrees(clashes::cyk_2, clashes, "cyk", "ab", 3);
+    agrees(clashes::GRAMMAR_0_2, clashes, "GRAMMAR_0", "c", 2);
+    agrees(clashes::Some_2, clashes, "Some", "d", 2);
+}
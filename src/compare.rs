@@ -18,13 +18,15 @@ fn compare(a: TerminalRef, b: TerminalRef) -> Ordering {
 
 impl Comparison {
     pub fn from_grammars(gram1: Grammar, gram2: Grammar, limit: usize) -> Self {
+        Self::from_producers(Producer::new(gram1), Producer::new(gram2), limit)
+    }
+
+    /// compares the words of two producers, which allows limiting their search space
+    pub fn from_producers(mut prod1: Producer, mut prod2: Producer, limit: usize) -> Self {
         let mut first = Vec::new();
         let mut second = Vec::new();
         let mut both = Vec::new();
 
-        let mut prod1 = Producer::new(gram1);
-        let mut prod2 = Producer::new(gram2);
-
         for _ in 0..limit {
             prod1.next();
         }
@@ -0,0 +1,101 @@
+use crate::bnf::{self, BnfRule};
+use crate::chomsky::{Definition, Grammar};
+use crate::error::Error;
+use crate::testfile::{self, Expectation};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[cfg(test)]
+mod test;
+
+/// version of the compiled format, increased on incompatible changes
+const VERSION: u32 = 1;
+
+/// A rules file together with its grammar in normal form, so it doesn't need to be normalized
+/// again when it is used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compiled {
+    pub version: u32,
+    /// name of the rule the grammar was built for
+    pub start: String,
+    pub rules: Vec<BnfRule>,
+    /// test directives of the rules file
+    pub tests: Vec<Expectation>,
+    pub grammar: Grammar,
+}
+
+/// extension of compiled grammars
+pub const EXTENSION: &str = "json";
+
+/// returns whether a file is a compiled grammar instead of ebnf rules, going by its extension
+pub fn is_compiled(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+impl Compiled {
+    /// parses ebnf rules and builds the grammar of the rule named `start`, or the first rule
+    pub fn from_source(source: &str, start: Option<&str>) -> Result<Self, Error> {
+        let rules = bnf::from_source(source)?;
+        let start = bnf::root(&rules, start)?.to_string();
+        let grammar = Grammar::from_rules(&rules, Some(&start))?;
+        let tests = testfile::directives(source).map_err(Error::Directives)?;
+        Ok(Self {
+            version: VERSION,
+            start,
+            rules,
+            tests,
+            grammar,
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let compiled: Self =
+            serde_json::from_str(json).map_err(|e| Error::InvalidCompiled(e.to_string()))?;
+        if compiled.version != VERSION {
+            return Err(Error::InvalidCompiled(format!(
+                "unsupported version {}, expected {}",
+                compiled.version, VERSION
+            )));
+        }
+        compiled.check_indices()?;
+        Ok(compiled)
+    }
+
+    /// makes sure that the start symbol and all products refer to rules of the grammar
+    fn check_indices(&self) -> Result<(), Error> {
+        let rules = self.grammar.rules.len();
+        if self.grammar.start >= rules {
+            return Err(Error::InvalidCompiled(format!(
+                "start symbol {} out of range, the grammar has {} rules",
+                self.grammar.start, rules
+            )));
+        }
+        for (i, rule) in self.grammar.rules.iter().enumerate() {
+            for definition in rule {
+                if let Definition::Product(product) = definition {
+                    if let Some(nt) = product.iter().find(|&&nt| nt >= rules) {
+                        return Err(Error::InvalidCompiled(format!(
+                            "rule {} refers to rule {}, but the grammar has {} rules",
+                            i, nt, rules
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("compiled grammars are always serializable")
+    }
+
+    /// returns the grammar of the rule named `start`, or of the compiled start rule
+    ///
+    /// Only grammars of other rules need to be normalized again.
+    pub fn grammar(&self, start: Option<&str>) -> Result<Grammar, Error> {
+        match start {
+            Some(start) if start != self.start => Grammar::from_rules(&self.rules, Some(start)),
+            _ => Ok(self.grammar.clone()),
+        }
+    }
+}
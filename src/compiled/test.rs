@@ -0,0 +1,138 @@
+use super::*;
+use crate::chomsky::Options;
+use crate::scratch::scratch;
+
+const SOURCE: &str = "<s> <= <t> {<t>}\n<t> <= a | b\n@accept <s> ab\n@reject <t> ab";
+
+#[test]
+fn round_trip() {
+    let compiled = Compiled::from_source(SOURCE, None).unwrap();
+    assert_eq!(compiled.start, "s");
+    assert_eq!(compiled.rules.len(), 2);
+    assert_eq!(compiled.tests, testfile::directives(SOURCE).unwrap());
+
+    let read = Compiled::from_json(&compiled.to_json()).unwrap();
+    assert_eq!(read, compiled);
+    assert!(read.grammar(None).unwrap().accepts("abba"));
+    assert!(!read.grammar(None).unwrap().accepts(""));
+    // other rules are normalized again
+    assert!(!read.grammar(Some("t")).unwrap().accepts("ab"));
+    assert!(matches!(
+        read.grammar(Some("u")),
+        Err(Error::UnknownRule { .. })
+    ));
+}
+
+#[test]
+fn invalid_directives() {
+    let source = "<s> <= a\n@accept s a\n@check <s> a";
+    match Compiled::from_source(source, None) {
+        Err(Error::Directives(errors)) => {
+            let lines = errors.iter().map(|e| e.line).collect::<Vec<_>>();
+            assert_eq!(lines, vec![2, 3]);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn invalid_json() {
+    let json = Compiled::from_source(SOURCE, None).unwrap().to_json();
+    let old = json.replacen(
+        &format!("\"version\": {}", VERSION),
+        &format!("\"version\": {}", VERSION + 1),
+        1,
+    );
+    assert_ne!(old, json);
+    match Compiled::from_json(&old) {
+        Err(Error::InvalidCompiled(reason)) => assert_eq!(
+            reason,
+            format!("unsupported version {}, expected {}", VERSION + 1, VERSION)
+        ),
+        other => panic!("{:?}", other),
+    }
+
+    for corrupt in ["", "{}", &json[..json.len() / 2], SOURCE] {
+        let result = Compiled::from_json(corrupt);
+        assert!(
+            matches!(result, Err(Error::InvalidCompiled(_))),
+            "{:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn invalid_indices() {
+    let compiled = Compiled::from_source(SOURCE, None).unwrap();
+    let rules = compiled.grammar.rules.len();
+
+    let mut start = compiled.clone();
+    start.grammar.start = 99;
+    let mut product = compiled.clone();
+    let (i, j) = product
+        .grammar
+        .rules
+        .iter()
+        .enumerate()
+        .find_map(|(i, rule)| {
+            let j = rule
+                .iter()
+                .position(|d| matches!(d, Definition::Product(_)))?;
+            Some((i, j))
+        })
+        .unwrap();
+    product.grammar.rules[i][j] = Definition::Product([0, rules]);
+
+    for (corrupt, reason) in [
+        (
+            start,
+            format!(
+                "start symbol 99 out of range, the grammar has {} rules",
+                rules
+            ),
+        ),
+        (
+            product,
+            format!(
+                "rule {} refers to rule {}, but the grammar has {} rules",
+                i, rules, rules
+            ),
+        ),
+    ] {
+        match Compiled::from_json(&corrupt.to_json()) {
+            Err(Error::InvalidCompiled(found)) => assert_eq!(found, reason),
+            other => panic!("{:?}", other),
+        }
+    }
+}
+
+#[test]
+fn compiled_files() {
+    assert!(is_compiled(Path::new("rules/scream.json")));
+    assert!(!is_compiled(Path::new("rules/scream")));
+    assert!(!is_compiled(Path::new("json")));
+
+    let (file, _guard) = scratch("compiled", "grammar.json");
+    let json = Compiled::from_source(SOURCE, None).unwrap().to_json();
+    std::fs::write(&file, &json).unwrap();
+    let grammar = Grammar::from_file(&file, &Options::new()).unwrap();
+    assert!(grammar.accepts("ab"));
+    let grammar = Grammar::from_file(&file, &Options::new().start("t")).unwrap();
+    assert!(grammar.accepts("a") && !grammar.accepts("ab"));
+
+    // without the extension, the file is read as ebnf rules
+    let other = file.with_extension("txt");
+    std::fs::write(&other, &json).unwrap();
+    let result = Grammar::from_file(&other, &Options::new());
+    assert!(matches!(result, Err(Error::Syntax(_))), "{:?}", result);
+
+    // and ebnf rules with the extension are invalid compiled grammars
+    std::fs::write(&file, SOURCE).unwrap();
+    let result = Grammar::from_file(&file, &Options::new());
+    assert!(
+        matches!(result, Err(Error::InvalidCompiled(_))),
+        "{:?}",
+        result
+    );
+}
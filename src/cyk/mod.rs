@@ -0,0 +1,341 @@
+use crate::chomsky::{Definition, Grammar, NonTerminal, Terminal};
+use std::collections::HashMap;
+
+/// Number of nonterminals stored in one block of a bitset
+const BITS: usize = 64;
+
+/// A set of nonterminals, stored in blocks of bits
+type Block = u64;
+
+#[cfg(test)]
+mod test;
+mod valiant;
+
+/// Precomputed indices of a grammar for the CYK algorithm on sets of nonterminals
+///
+/// Every cell of the table is a bitset of the nonterminals that produce the corresponding
+/// subword, and products are looked up by their left nonterminal, so only pairs that actually
+/// occur in the table are considered. Building it once and reusing it saves work when checking
+/// many words against the same grammar, see also [`accepts_all`](Self::accepts_all).
+#[derive(Debug, Clone)]
+pub struct Cyk {
+    start: NonTerminal,
+    null: bool,
+    /// number of blocks of a bitset
+    width: usize,
+    /// terminals with the nonterminals producing them, by their last character
+    terms: HashMap<char, Vec<(Terminal, NonTerminal)>>,
+    /// for every nonterminal `B`, the pairs of `C` and the set of all `A` with `A -> B C`
+    products: Vec<Vec<(NonTerminal, Vec<Block>)>>,
+    /// all nonterminals with a product, once a cell contains them the other splits are skipped
+    heads: Vec<Block>,
+}
+
+/// The CYK table of a word, one bitset for every subword
+///
+/// The cells are stored by the end of their subword, so the cells of a prefix don't depend on
+/// the rest of the word and can be kept for the next word with the same prefix.
+struct Table {
+    width: usize,
+    chars: Vec<char>,
+    cells: Vec<Block>,
+    /// whether any nonterminal produces the subword, to skip empty cells quickly
+    filled: Vec<bool>,
+}
+
+impl Table {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            chars: Vec::new(),
+            cells: Vec::new(),
+            filled: Vec::new(),
+        }
+    }
+
+    /// index of the subword `start..end`
+    fn index(start: usize, end: usize) -> usize {
+        end * (end - 1) / 2 + start
+    }
+
+    fn cell(&self, start: usize, end: usize) -> &[Block] {
+        let i = Self::index(start, end) * self.width;
+        &self.cells[i..i + self.width]
+    }
+
+    fn insert(&mut self, start: usize, end: usize, nt: NonTerminal) {
+        let i = Self::index(start, end);
+        self.cells[i * self.width + nt / BITS] |= 1 << (nt % BITS);
+        self.filled[i] = true;
+    }
+
+    fn contains(&self, start: usize, end: usize, nt: NonTerminal) -> bool {
+        self.cell(start, end)[nt / BITS] & (1 << (nt % BITS)) != 0
+    }
+
+    /// forgets everything after the first `len` characters
+    fn truncate(&mut self, len: usize) {
+        let cells = len * (len + 1) / 2;
+        self.chars.truncate(len);
+        self.cells.truncate(cells * self.width);
+        self.filled.truncate(cells);
+    }
+}
+
+/// iterates over the elements of a bitset
+fn elements(set: &[Block]) -> impl Iterator<Item = NonTerminal> + '_ {
+    set.iter().enumerate().flat_map(|(b, &block)| {
+        let mut block = block;
+        std::iter::from_fn(move || {
+            if block == 0 {
+                return None;
+            }
+            let bit = block.trailing_zeros() as usize;
+            block &= block - 1;
+            Some(b * BITS + bit)
+        })
+    })
+}
+
+impl Cyk {
+    pub fn new(grammar: &Grammar) -> Self {
+        let width = grammar.rules.len().div_ceil(BITS);
+        let mut terms = HashMap::<char, Vec<_>>::new();
+        let mut pairs = vec![HashMap::<NonTerminal, Vec<Block>>::new(); grammar.rules.len()];
+        for (a, rule) in grammar.rules.iter().enumerate() {
+            for def in rule.iter() {
+                match def {
+                    Definition::Term(term) => {
+                        if let Some(&last) = term.last() {
+                            terms.entry(last).or_default().push((term.clone(), a));
+                        }
+                    }
+                    Definition::Product([b, c]) => {
+                        let set = pairs[*b].entry(*c).or_insert_with(|| vec![0; width]);
+                        set[a / BITS] |= 1 << (a % BITS);
+                    }
+                }
+            }
+        }
+        let mut heads = vec![0; width];
+        for a in pairs.iter().flat_map(|p| p.values()) {
+            heads.iter_mut().zip(a.iter()).for_each(|(h, a)| *h |= a);
+        }
+        let products = pairs
+            .into_iter()
+            .map(|p| {
+                let mut p = p.into_iter().collect::<Vec<_>>();
+                p.sort_unstable();
+                p
+            })
+            .collect();
+        Self {
+            start: grammar.start,
+            null: grammar.null,
+            width,
+            terms,
+            products,
+            heads,
+        }
+    }
+
+    /// Words up to this length are always checked with the table, for longer ones the cost of
+    /// the table and of [`accepts_matrix`](Self::accepts_matrix) is estimated first
+    pub const MATRIX_THRESHOLD: usize = 128;
+
+    /// Checks if a word is accepted by the grammar
+    pub fn accepts(&self, word: &str) -> bool {
+        let chars = word.chars().collect::<Vec<_>>();
+        let mut table = Table::new(self.width);
+        if chars.len() > Self::MATRIX_THRESHOLD {
+            match self.sample(&chars) {
+                Some(sample) => table = sample,
+                None => return self.accepts_matrix(word),
+            }
+        }
+        let start = table.chars.len();
+        chars[start..].iter().for_each(|&c| {
+            self.push(&mut table, c);
+        });
+        self.verdict(&table)
+    }
+
+    /// fills a table with a prefix of the word to continue from, or returns `None` if Boolean
+    /// matrix multiplication is estimated to check the whole word faster
+    ///
+    /// Both depend on how many nonterminals produce the subwords, which varies a lot between
+    /// grammars: the table takes quadratic time if few subwords are produced, and cubic time if
+    /// most are. So both methods are run on a prefix of the word and on its first half, and the
+    /// work they do is extrapolated to the whole word with the growth measured between the two.
+    fn sample(&self, chars: &[char]) -> Option<Table> {
+        /// length of the prefix
+        const SAMPLE: usize = 64;
+        /// number of cells the table skips as empty in the time it looks up one product
+        const SKIPS_PER_LOOKUP: f64 = 32.0;
+
+        let mut table = Table::new(self.width);
+        let mut half = 0;
+        let mut lookups = 0;
+        for (i, &c) in chars[..SAMPLE].iter().enumerate() {
+            if i == SAMPLE / 2 {
+                half = lookups;
+            }
+            lookups += self.push(&mut table, c);
+        }
+        let table_work = (half, lookups);
+        let matrix_work = (
+            self.matrix_work(&chars[..SAMPLE / 2]).1,
+            self.matrix_work(&chars[..SAMPLE]).1,
+        );
+
+        let scale = chars.len() as f64 / SAMPLE as f64;
+        let extrapolate = |(half, full): (usize, usize)| {
+            let growth = (full as f64 / half.max(1) as f64).log2().clamp(1.0, 3.0);
+            full as f64 * scale.powf(growth)
+        };
+        let skips = (chars.len() as f64).powi(3) / 6.0 / SKIPS_PER_LOOKUP;
+        if extrapolate(matrix_work) < extrapolate(table_work) + skips {
+            return None;
+        }
+        Some(table)
+    }
+
+    /// Checks if a word is accepted by the grammar with the CYK table, regardless of its length
+    pub fn accepts_table(&self, word: &str) -> bool {
+        let mut table = Table::new(self.width);
+        word.chars().for_each(|c| {
+            self.push(&mut table, c);
+        });
+        self.verdict(&table)
+    }
+
+    /// Checks many words at once, the verdicts are in the same order as the words
+    ///
+    /// The words are sorted and split among as many threads as there are cores, but at most one
+    /// per word, and words that share a prefix with the one checked before also share its part
+    /// of the table.
+    /// Long words without such a prefix may be checked by [`accepts_matrix`](Self::accepts_matrix),
+    /// as in [`accepts`](Self::accepts).
+    pub fn accepts_all<S: AsRef<str> + Sync>(&self, words: &[S]) -> Vec<bool> {
+        let mut order = (0..words.len()).collect::<Vec<_>>();
+        order.sort_unstable_by(|&a, &b| words[a].as_ref().cmp(words[b].as_ref()));
+        let chunk = order.len().div_ceil(threads(words.len())).max(1);
+
+        let mut verdicts = vec![false; words.len()];
+        std::thread::scope(|scope| {
+            let handles = order
+                .chunks(chunk)
+                .map(|chunk| scope.spawn(move || (chunk, self.accepts_sorted(words, chunk))))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                let (chunk, accepts) = handle.join().unwrap();
+                for (&i, accepts) in chunk.iter().zip(accepts) {
+                    verdicts[i] = accepts;
+                }
+            }
+        });
+        verdicts
+    }
+
+    /// checks the words at `order`, reusing the table of common prefixes
+    fn accepts_sorted<S: AsRef<str>>(&self, words: &[S], order: &[usize]) -> Vec<bool> {
+        let mut table = Table::new(self.width);
+        let mut verdicts = Vec::with_capacity(order.len());
+        for &i in order.iter() {
+            let chars = words[i].as_ref().chars().collect::<Vec<_>>();
+            let mut common = chars
+                .iter()
+                .zip(table.chars.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            // the table pays off for short words, if most of it is shared, or for grammars that
+            // produce few subwords
+            if chars.len() - common > Self::MATRIX_THRESHOLD {
+                match self.sample(&chars) {
+                    // the sampled prefix is of no use if a longer one is shared anyway
+                    Some(sample) if sample.chars.len() > common => {
+                        common = sample.chars.len();
+                        table = sample;
+                    }
+                    Some(_) => {}
+                    None => {
+                        verdicts.push(self.accepts_matrix(words[i].as_ref()));
+                        continue;
+                    }
+                }
+            }
+            table.truncate(common);
+            chars[common..].iter().for_each(|&c| {
+                self.push(&mut table, c);
+            });
+            verdicts.push(self.verdict(&table));
+        }
+        verdicts
+    }
+
+    /// whether the start symbol produces the whole word of the table
+    fn verdict(&self, table: &Table) -> bool {
+        match table.chars.len() {
+            0 => self.null,
+            n => table.contains(0, n, self.start),
+        }
+    }
+
+    /// appends a character to the word of the table and fills the cells of all subwords that
+    /// end with it
+    ///
+    /// Returns the number of products it looked up, as a measure of the work it did.
+    fn push(&self, table: &mut Table, c: char) -> usize {
+        let width = self.width;
+        table.chars.push(c);
+        let end = table.chars.len();
+        table.cells.resize(table.cells.len() + end * width, 0);
+        table.filled.resize(table.filled.len() + end, false);
+
+        for (term, a) in self.terms.get(&c).into_iter().flatten() {
+            if table.chars.ends_with(term) {
+                table.insert(end - term.len(), end, *a);
+            }
+        }
+
+        let mut lookups = 0;
+        let mut set = vec![0; width];
+        for start in (0..end - 1).rev() {
+            set.iter_mut().for_each(|b| *b = 0);
+            for split in start + 1..end {
+                if set == self.heads {
+                    break;
+                }
+                let left = Table::index(start, split);
+                let right = Table::index(split, end);
+                if !table.filled[left] || !table.filled[right] {
+                    continue;
+                }
+                let right = &table.cells[right * width..(right + 1) * width];
+                for b in elements(&table.cells[left * width..(left + 1) * width]) {
+                    lookups += self.products[b].len();
+                    for (c, a) in self.products[b].iter() {
+                        if right[c / BITS] & (1 << (c % BITS)) != 0 {
+                            set.iter_mut().zip(a.iter()).for_each(|(s, a)| *s |= a);
+                        }
+                    }
+                }
+            }
+            if set.iter().any(|&b| b != 0) {
+                let i = Table::index(start, end);
+                let cell = &mut table.cells[i * width..(i + 1) * width];
+                cell.iter_mut().zip(set.iter()).for_each(|(c, s)| *c |= s);
+                table.filled[i] = true;
+            }
+        }
+        lookups
+    }
+}
+
+/// number of threads that check `words` words at once, one per core but at most one per word
+fn threads(words: usize) -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(words)
+        .max(1)
+}
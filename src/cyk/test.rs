@@ -0,0 +1,189 @@
+use super::*;
+use crate::bnf::words::{assert_agree, words};
+use crate::chomsky::Options;
+
+fn agrees(source: &str, start: &str, alphabet: &str, max: usize) {
+    let grammar = Grammar::from_source(source, &Options::new().start(start)).unwrap();
+    let cyk = Cyk::new(&grammar);
+    assert_agree(
+        &words(alphabet, max),
+        |w| grammar.accepts_naive(w),
+        |w| cyk.accepts(w),
+        &format!("<{}>", start),
+    );
+}
+
+#[test]
+fn bundled_rules() {
+    let div3 = include_str!("../../rules/binary-div3");
+    for start in ["0", "1", "2"] {
+        agrees(div3, start, "01", 8);
+    }
+    agrees(include_str!("../../rules/scream"), "scream", "aAb", 6);
+    agrees(
+        include_str!("../../rules/long-scream"),
+        "long-scream",
+        "aAb",
+        6,
+    );
+}
+
+#[test]
+fn multi_char_terminals() {
+    let source = "<s> <= ab | <s> <s> | a <s> b | ba";
+    agrees(source, "s", "ab", 9);
+}
+
+#[test]
+fn many_nonterminals() {
+    // more than one block of nonterminals per cell
+    let mut source = String::from("<r0> <= a | b <r1>\n");
+    for i in 1..100 {
+        source.push_str(&format!(
+            "<r{}> <= {} | <r{}> <r{}>\n",
+            i,
+            i % 3,
+            i - 1,
+            i + 1
+        ));
+    }
+    source.push_str("<r100> <= ab\n");
+    let grammar = Grammar::from_source(&source, &Options::new()).unwrap();
+    assert!(grammar.rules.len() > BITS);
+    agrees(&source, "r0", "ab012", 5);
+}
+
+#[test]
+fn empty_word() {
+    let grammar = Grammar::from_source("<s> <= [a]", &Options::new()).unwrap();
+    assert!(Cyk::new(&grammar).accepts(""));
+    let grammar = Grammar::from_source("<s> <= a", &Options::new()).unwrap();
+    assert!(!Cyk::new(&grammar).accepts(""));
+}
+
+#[test]
+fn batch() {
+    let div3 = include_str!("../../rules/binary-div3");
+    let grammar = Grammar::from_source(div3, &Options::new()).unwrap();
+    let cyk = Cyk::new(&grammar);
+    let mut words = words("01", 7);
+    // unsorted, with duplicates and words that aren't prefixes of each other
+    words.reverse();
+    words.extend(words.clone().into_iter().step_by(3));
+    words.push("0102".to_string());
+    let verdicts = cyk.accepts_all(&words);
+    assert_eq!(verdicts.len(), words.len());
+    for (word, accepts) in words.iter().zip(verdicts) {
+        assert_eq!(accepts, grammar.accepts_naive(word), "'{}'", word);
+    }
+    assert!(cyk.accepts_all::<&str>(&[]).is_empty());
+}
+
+#[test]
+fn matrix_agrees() {
+    let div3 = include_str!("../../rules/binary-div3");
+    for (source, start, alphabet) in [
+        (div3, "0", "01"),
+        ("<s> <= ab | <s> <s> | a <s> b | ba", "s", "ab"),
+        (
+            include_str!("../../rules/long-scream"),
+            "long-scream",
+            "aAb",
+        ),
+    ] {
+        let grammar = Grammar::from_source(source, &Options::new().start(start)).unwrap();
+        let cyk = Cyk::new(&grammar);
+        let long = (60..140).map(|n| alphabet.chars().cycle().take(n).collect::<String>());
+        let words = words(alphabet, 7)
+            .into_iter()
+            .chain(long)
+            .collect::<Vec<_>>();
+        assert_agree(
+            &words,
+            |w| cyk.accepts_table(w),
+            |w| cyk.accepts_matrix(w),
+            &format!("<{}>", start),
+        );
+    }
+}
+
+/// compares both recognizers on random concatenations of produced words, some of them mutated
+#[test]
+fn matrix_agrees_on_produced_words() {
+    use crate::producer::Producer;
+    use rand::prelude::*;
+
+    let mut rng = rand::thread_rng();
+    let div3 = include_str!("../../rules/binary-div3");
+    for (source, start) in [
+        (div3, "2"),
+        ("<s> <= ab | <s> <s> | a <s> b | ba", "s"),
+        ("<e> <= <e>+<e> | <e>*<e> | (\\(<e>\\)) | x", "e"),
+        (include_str!("../../rules/long-scream"), "long-scream"),
+    ] {
+        let grammar = Grammar::from_source(source, &Options::new().start(start)).unwrap();
+        let cyk = Cyk::new(&grammar);
+        let produced = Producer::new(grammar).take(200).collect::<Vec<_>>();
+        let alphabet = produced.iter().flatten().copied().collect::<Vec<_>>();
+        for _ in 0..300 {
+            let mut word = Vec::new();
+            for _ in 0..1 + rng.gen::<usize>() % 6 {
+                word.extend(produced.choose(&mut rng).unwrap());
+            }
+            if !word.is_empty() && rng.gen() {
+                let i = rng.gen::<usize>() % word.len();
+                match rng.gen::<usize>() % 3 {
+                    0 => word[i] = *alphabet.choose(&mut rng).unwrap(),
+                    1 => word.insert(i, *alphabet.choose(&mut rng).unwrap()),
+                    _ => drop(word.remove(i)),
+                }
+            }
+            let word = word.into_iter().collect::<String>();
+            assert_eq!(
+                cyk.accepts_matrix(&word),
+                cyk.accepts_table(&word),
+                "'{}'",
+                word
+            );
+        }
+    }
+}
+
+/// long words go to the matrix for dense tables only, and continue from the sampled table
+/// otherwise
+#[test]
+fn backend_choice() {
+    let grammar = |source, start| Grammar::from_source(source, &Options::new().start(start));
+    let div3 = Cyk::new(&grammar(include_str!("../../rules/binary-div3"), "0").unwrap());
+    let scream = Cyk::new(&grammar(include_str!("../../rules/scream"), "scream").unwrap());
+
+    let binary = "011".repeat(160).chars().collect::<Vec<_>>();
+    assert!(div3.sample(&binary).is_none());
+    let screamed = "aA".repeat(240);
+    assert!(scream
+        .sample(&screamed.chars().collect::<Vec<_>>())
+        .is_some());
+
+    let words = [
+        screamed.clone(),
+        format!("{}a", screamed),
+        format!("{}b", screamed),
+        format!("aA{}", screamed),
+    ];
+    let verdicts = words.iter().map(|w| scream.accepts(w)).collect::<Vec<_>>();
+    let table = words.iter().map(|w| scream.accepts_table(w));
+    assert_eq!(verdicts, table.collect::<Vec<_>>());
+    assert!(verdicts[0] && !verdicts[2]);
+    assert_eq!(scream.accepts_all(&words), verdicts);
+    assert!(div3.accepts(&"011".repeat(160)));
+    assert!(!div3.accepts(&format!("1{}", "011".repeat(160))));
+}
+
+#[test]
+fn thread_count() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    assert_eq!(threads(0), 1);
+    assert_eq!(threads(1), 1);
+    assert_eq!(threads(2), cores.min(2));
+    assert_eq!(threads(100_000), cores);
+}
@@ -0,0 +1,171 @@
+//! Recognition by Boolean matrix multiplication
+//!
+//! This follows Okhotin's formulation of [Valiant's algorithm](https://doi.org/10.1016/S0022-0000(75)80046-8):
+//! the table is filled by recursively splitting it into blocks, and the products of two finished
+//! blocks are added to a third one as a whole. With one bit matrix per nonterminal, such a
+//! product handles 64 cells at once, where CYK looks at every cell on its own.
+
+use super::{elements, Block, Cyk, BITS};
+use crate::chomsky::NonTerminal;
+
+/// Half-open range of positions in the word
+type Range = (usize, usize);
+
+/// One square bit matrix per nonterminal, with a bit at `(i, j)` if it produces `word[i..j]`
+struct Matrices {
+    /// number of positions, i.e. the length of the word plus one
+    size: usize,
+    /// blocks per row
+    width: usize,
+    rows: Vec<Block>,
+}
+
+impl Matrices {
+    fn new(nonterminals: usize, size: usize) -> Self {
+        let width = size.div_ceil(BITS);
+        Self {
+            size,
+            width,
+            rows: vec![0; nonterminals * size * width],
+        }
+    }
+
+    /// offset of row `i` of the matrix of `nt`
+    fn row(&self, nt: NonTerminal, i: usize) -> usize {
+        (nt * self.size + i) * self.width
+    }
+
+    fn insert(&mut self, nt: NonTerminal, i: usize, j: usize) {
+        let row = self.row(nt, i);
+        self.rows[row + j / BITS] |= 1 << (j % BITS);
+    }
+
+    fn contains(&self, nt: NonTerminal, i: usize, j: usize) -> bool {
+        self.rows[self.row(nt, i) + j / BITS] & (1 << (j % BITS)) != 0
+    }
+}
+
+/// the blocks of a row that overlap with `range`, each with the mask of the bits within it
+fn blocks((lo, hi): Range) -> impl Iterator<Item = (usize, Block)> {
+    (lo / BITS..hi.div_ceil(BITS)).map(move |b| {
+        let mut mask = !0;
+        if b == lo / BITS {
+            mask &= !0 << (lo % BITS);
+        }
+        if hi - b * BITS < BITS {
+            mask &= (1 << (hi - b * BITS)) - 1;
+        }
+        (b, mask)
+    })
+}
+
+impl Cyk {
+    /// Checks if a word is accepted by the grammar, using Boolean matrix multiplication
+    ///
+    /// For long words this is faster than the table of [`accepts`](Self::accepts) if the
+    /// grammar produces many of their subwords. `accepts` switches to it by itself where it
+    /// estimates that to be the case, which is never for words of up to
+    /// [`Cyk::MATRIX_THRESHOLD`] characters.
+    pub fn accepts_matrix(&self, word: &str) -> bool {
+        self.matrix_work(&word.chars().collect::<Vec<_>>()).0
+    }
+
+    /// checks the word, and returns the verdict together with the number of row blocks that
+    /// were combined, as a measure of the work done
+    pub(super) fn matrix_work(&self, chars: &[char]) -> (bool, usize) {
+        if chars.is_empty() {
+            return (self.null, 0);
+        }
+        let n = chars.len();
+        let mut m = Matrices::new(self.products.len(), n + 1);
+        for end in 1..=n {
+            for (term, a) in self.terms.get(&chars[end - 1]).into_iter().flatten() {
+                if chars[..end].ends_with(term) {
+                    m.insert(*a, end - term.len(), end);
+                }
+            }
+        }
+        let pairs = self
+            .products
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .flat_map(|(c, a)| elements(a).map(move |a| (*c, a)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut valiant = Valiant { m, pairs, work: 0 };
+        valiant.compute((0, n + 1));
+        (valiant.m.contains(self.start, 0, n), valiant.work)
+    }
+}
+
+struct Valiant {
+    m: Matrices,
+    /// for every nonterminal `B`, all pairs of `C` and `A` with `A -> B C`
+    pairs: Vec<Vec<(NonTerminal, NonTerminal)>>,
+    /// number of row blocks combined so far
+    work: usize,
+}
+
+impl Valiant {
+    /// fills all cells `(i, j)` with `i` and `j` in `range`
+    fn compute(&mut self, (l, m): Range) {
+        if m - l < 2 {
+            return;
+        }
+        let mid = (l + m) / 2;
+        self.compute((l, mid));
+        self.compute((mid, m));
+        self.complete((l, mid), (mid, m));
+    }
+
+    /// fills the block of rows `rows` and columns `cols`
+    ///
+    /// The cells within `rows` and within `cols` have to be finished, and the block has to
+    /// contain the products over all positions between them already.
+    fn complete(&mut self, rows: Range, cols: Range) {
+        let (height, width) = (rows.1 - rows.0, cols.1 - cols.0);
+        if height == 1 && width == 1 {
+            return;
+        }
+        // the half closer to the diagonal goes first, it is a factor of the other one
+        if height >= width {
+            let mid = (rows.0 + rows.1) / 2;
+            self.complete((mid, rows.1), cols);
+            self.multiply((rows.0, mid), (mid, rows.1), cols);
+            self.complete((rows.0, mid), cols);
+        } else {
+            let mid = (cols.0 + cols.1) / 2;
+            self.complete(rows, (cols.0, mid));
+            self.multiply(rows, (cols.0, mid), (mid, cols.1));
+            self.complete(rows, (mid, cols.1));
+        }
+    }
+
+    /// adds the products of the blocks `rows × mid` and `mid × cols` to `rows × cols`
+    fn multiply(&mut self, rows: Range, mid: Range, cols: Range) {
+        let m = &mut self.m;
+        for (b, pairs) in self.pairs.iter().enumerate() {
+            if pairs.is_empty() {
+                continue;
+            }
+            for i in rows.0..rows.1 {
+                for (block, mask) in blocks(mid) {
+                    let mut bits = m.rows[m.row(b, i) + block] & mask;
+                    while bits != 0 {
+                        let k = block * BITS + bits.trailing_zeros() as usize;
+                        bits &= bits - 1;
+                        self.work += pairs.len() * (cols.1.div_ceil(BITS) - cols.0 / BITS);
+                        for &(c, a) in pairs.iter() {
+                            let (src, dst) = (m.row(c, k), m.row(a, i));
+                            for (block, mask) in blocks(cols) {
+                                m.rows[dst + block] |= m.rows[src + block] & mask;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
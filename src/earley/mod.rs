@@ -0,0 +1,244 @@
+use crate::chomsky::{Definition, Grammar, NonTerminal};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[cfg(test)]
+mod test;
+
+/// A definition of a rule, partially matched since `origin`
+///
+/// For a terminal, `dot` is the number of matched characters, for a product the number of
+/// matched nonterminals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: NonTerminal,
+    def: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// The items after some prefix of the input
+#[derive(Debug, Default)]
+struct Set {
+    items: Vec<Item>,
+    seen: HashSet<Item>,
+    /// items that wait for a nonterminal to be matched, by that nonterminal
+    waiting: HashMap<NonTerminal, Vec<usize>>,
+    predicted: HashSet<NonTerminal>,
+}
+
+impl Set {
+    fn add(&mut self, item: Item) {
+        if self.seen.insert(item) {
+            self.items.push(item);
+        }
+    }
+}
+
+/// Recognizes the words of a grammar character by character
+///
+/// It runs the [Earley algorithm](https://en.wikipedia.org/wiki/Earley_parser) on the grammar,
+/// without the definitions that can't produce any word. So as long as there are items left,
+/// the input seen so far is the prefix of some word, and it notices the first character after
+/// which that is no longer the case. The characters themselves aren't kept.
+#[derive(Debug)]
+pub struct Recognizer<'a> {
+    grammar: &'a Grammar,
+    /// whether a nonterminal produces any word
+    productive: Vec<bool>,
+    sets: Vec<Set>,
+    /// characters fed after the input stopped being viable, no sets are kept for them
+    skipped: usize,
+}
+
+impl<'a> Recognizer<'a> {
+    pub fn new(grammar: &'a Grammar) -> Self {
+        let mut recognizer = Self {
+            grammar,
+            productive: productive(grammar),
+            sets: Vec::new(),
+            skipped: 0,
+        };
+        recognizer.reset();
+        recognizer
+    }
+
+    /// forgets the input and starts over with the empty word
+    pub fn reset(&mut self) {
+        let mut set = Set::default();
+        self.predict(&mut set, self.grammar.start, 0);
+        self.sets = vec![set];
+        self.skipped = 0;
+        self.close();
+    }
+
+    /// number of characters fed so far
+    pub fn len(&self) -> usize {
+        self.sets.len() - 1 + self.skipped
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the next character of the input
+    ///
+    /// Returns whether the input is still the prefix of a word of the grammar, once it isn't,
+    /// no further input can change that.
+    pub fn feed(&mut self, c: char) -> bool {
+        if !self.is_viable() {
+            self.skipped += 1;
+            return false;
+        }
+        let mut next = Set::default();
+        for item in self.current().items.iter() {
+            if let Definition::Term(term) = &self.grammar.rules[item.rule][item.def] {
+                if term.get(item.dot) == Some(&c) {
+                    next.add(Item {
+                        dot: item.dot + 1,
+                        ..*item
+                    });
+                }
+            }
+        }
+        self.sets.push(next);
+        self.close();
+        self.is_viable()
+    }
+
+    /// feeds all characters of `s`, and returns whether the input is still viable
+    pub fn feed_str(&mut self, s: &str) -> bool {
+        s.chars().for_each(|c| {
+            self.feed(c);
+        });
+        self.is_viable()
+    }
+
+    /// Whether the input is a word of the grammar
+    pub fn is_accepting(&self) -> bool {
+        if self.is_empty() {
+            return self.grammar.null;
+        }
+        self.current()
+            .items
+            .iter()
+            .any(|item| item.rule == self.grammar.start && item.origin == 0 && self.complete(item))
+    }
+
+    /// Whether more characters can make the input a word of the grammar
+    pub fn can_continue(&self) -> bool {
+        self.current().items.iter().any(|item| !self.complete(item))
+    }
+
+    /// whether the input is a word or the prefix of a word of the grammar
+    pub fn is_viable(&self) -> bool {
+        self.is_accepting() || self.can_continue()
+    }
+
+    /// the characters that can come next
+    pub fn expected(&self) -> BTreeSet<char> {
+        let rules = &self.grammar.rules;
+        self.current()
+            .items
+            .iter()
+            .filter_map(|item| match &rules[item.rule][item.def] {
+                Definition::Term(term) => term.get(item.dot).copied(),
+                Definition::Product(_) => None,
+            })
+            .collect()
+    }
+
+    /// the last set, which stays the same once the input isn't viable anymore
+    fn current(&self) -> &Set {
+        self.sets.last().unwrap()
+    }
+
+    fn complete(&self, item: &Item) -> bool {
+        match &self.grammar.rules[item.rule][item.def] {
+            Definition::Term(term) => item.dot == term.len(),
+            Definition::Product(_) => item.dot == 2,
+        }
+    }
+
+    /// adds the productive definitions of `rule` to `set`
+    fn predict(&self, set: &mut Set, rule: NonTerminal, origin: usize) {
+        if !set.predicted.insert(rule) {
+            return;
+        }
+        for (def, definition) in self.grammar.rules[rule].iter().enumerate() {
+            let productive = match definition {
+                Definition::Term(term) => !term.is_empty(),
+                Definition::Product([b, c]) => self.productive[*b] && self.productive[*c],
+            };
+            if productive {
+                set.add(Item {
+                    rule,
+                    def,
+                    dot: 0,
+                    origin,
+                });
+            }
+        }
+    }
+
+    /// predicts and completes the items of the last set
+    ///
+    /// Every definition produces at least one character, so completed items always start in
+    /// an earlier set.
+    fn close(&mut self) {
+        let k = self.sets.len() - 1;
+        let mut set = self.sets.pop().unwrap();
+        let mut i = 0;
+        while i < set.items.len() {
+            let item = set.items[i];
+            match self.grammar.rules[item.rule][item.def] {
+                Definition::Product(nts) if item.dot < 2 => {
+                    let next = nts[item.dot];
+                    set.waiting.entry(next).or_default().push(i);
+                    self.predict(&mut set, next, k);
+                }
+                _ if self.complete(&item) => {
+                    let origin = &self.sets[item.origin];
+                    for &parent in origin.waiting.get(&item.rule).into_iter().flatten() {
+                        let parent = origin.items[parent];
+                        set.add(Item {
+                            dot: parent.dot + 1,
+                            ..parent
+                        });
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        self.sets.push(set);
+    }
+}
+
+/// which nonterminals produce at least one word
+fn productive(grammar: &Grammar) -> Vec<bool> {
+    let mut productive = grammar
+        .rules
+        .iter()
+        .map(|rule| {
+            rule.iter()
+                .any(|def| matches!(def, Definition::Term(term) if !term.is_empty()))
+        })
+        .collect::<Vec<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (a, rule) in grammar.rules.iter().enumerate() {
+            if productive[a] {
+                continue;
+            }
+            if rule.iter().any(|def| match def {
+                Definition::Product([b, c]) => productive[*b] && productive[*c],
+                Definition::Term(_) => false,
+            }) {
+                productive[a] = true;
+                changed = true;
+            }
+        }
+    }
+    productive
+}
@@ -0,0 +1,95 @@
+use super::*;
+use crate::bnf::words::{assert_agree, words};
+use crate::chomsky::Options;
+use crate::cyk::Cyk;
+
+fn grammar(source: &str, start: &str) -> Grammar {
+    Grammar::from_source(source, &Options::new().start(start)).unwrap()
+}
+
+/// checks the verdicts for all words up to length `max`, a viable word has to be the prefix of
+/// an accepted word that is at most `extra` characters longer
+fn agrees(grammar: &Grammar, alphabet: &str, max: usize, extra: usize) {
+    let cyk = Cyk::new(grammar);
+    let accepted = words(alphabet, max + extra)
+        .into_iter()
+        .filter(|w| cyk.accepts(w))
+        .collect::<Vec<_>>();
+    let words = words(alphabet, max);
+    assert_agree(
+        &words,
+        |w| cyk.accepts(w),
+        |w| {
+            let mut recognizer = Recognizer::new(grammar);
+            recognizer.feed_str(w);
+            recognizer.is_accepting()
+        },
+        "accepting",
+    );
+    assert_agree(
+        &words,
+        |w| accepted.iter().any(|a| a.starts_with(w)),
+        |w| Recognizer::new(grammar).feed_str(w),
+        "viable",
+    );
+}
+
+#[test]
+fn bundled_rules() {
+    let div3 = include_str!("../../rules/binary-div3");
+    agrees(&grammar(div3, "1"), "01", 7, 2);
+    agrees(
+        &grammar(include_str!("../../rules/scream"), "scream"),
+        "aAb",
+        5,
+        1,
+    );
+    let long_scream = include_str!("../../rules/long-scream");
+    agrees(&grammar(long_scream, "long-scream"), "aAb", 5, 3);
+}
+
+#[test]
+fn nested() {
+    agrees(&grammar("<s> <= a <s> b | c | ab", "s"), "abc", 4, 5);
+    agrees(&grammar("<s> <= xyz | x{y}x", "s"), "xyz", 5, 3);
+}
+
+#[test]
+fn first_bad_character() {
+    let g = grammar(include_str!("../../rules/long-scream"), "long-scream");
+    let mut recognizer = Recognizer::new(&g);
+    assert!(recognizer.feed('a') && recognizer.feed('A'));
+    assert!(!recognizer.is_accepting() && recognizer.can_continue());
+    assert!(recognizer.feed('a') && recognizer.is_accepting());
+    assert_eq!(recognizer.expected(), "Aa".chars().collect());
+    assert!(!recognizer.feed('!'));
+    assert!(!recognizer.feed('a'));
+    assert_eq!(recognizer.len(), 5);
+    // no sets are kept for the input after the first bad character
+    assert!(!recognizer.feed_str(&"a".repeat(1000)));
+    assert_eq!(recognizer.len(), 1005);
+    assert_eq!(recognizer.sets.len(), 5);
+
+    recognizer.reset();
+    assert!(recognizer.is_empty() && !recognizer.is_accepting());
+    assert!(recognizer.feed_str("AAAA"));
+}
+
+#[test]
+fn unproductive() {
+    let g = grammar("<s> <= a <dead> | ab\n<dead> <= x <dead>", "s");
+    let mut recognizer = Recognizer::new(&g);
+    assert!(recognizer.feed('a'));
+    assert_eq!(recognizer.expected(), "b".chars().collect());
+    assert!(!recognizer.feed('x'));
+}
+
+#[test]
+fn empty_word() {
+    let g = grammar("<s> <= [a]", "s");
+    let recognizer = Recognizer::new(&g);
+    assert!(recognizer.is_accepting() && recognizer.can_continue());
+    let g = grammar("<s> <= <s> a", "s");
+    let recognizer = Recognizer::new(&g);
+    assert!(!recognizer.is_viable());
+}
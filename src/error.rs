@@ -0,0 +1,70 @@
+use crate::parse::ParseError;
+use crate::testfile::DirectiveError;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong on the way from a rules file to a grammar in normal form
+#[derive(Debug)]
+pub enum Error {
+    /// the file couldn't be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// the rules contain syntax errors
+    Syntax(Vec<ParseError>),
+    /// the test directives of the rules contain errors
+    Directives(Vec<DirectiveError>),
+    /// there are no rules that could be used as start
+    NoRules,
+    /// the requested start rule doesn't exist
+    UnknownRule {
+        name: String,
+        /// names of all rules, in order of their first definition
+        available: Vec<String>,
+    },
+    /// the grammar couldn't be brought into normal form, which is a bug in parsley
+    Normalization(String),
+    /// a compiled grammar couldn't be read
+    InvalidCompiled(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io { path, .. } => {
+                write!(
+                    f,
+                    "failed to read file {}",
+                    path.as_os_str().to_string_lossy()
+                )
+            }
+            Self::Syntax(errors) => write!(f, "{} syntax errors", errors.len()),
+            Self::Directives(errors) => write!(f, "{} invalid test directives", errors.len()),
+            Self::NoRules => write!(f, "the file doesn't contain any rules"),
+            Self::UnknownRule { name, available } => write!(
+                f,
+                "there is no rule named <{}>, available rules are: {}",
+                name,
+                available
+                    .iter()
+                    .map(|n| format!("<{}>", n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Normalization(_) => write!(f, "internal error: failed to normalize grammar"),
+            Self::InvalidCompiled(reason) => write!(f, "invalid compiled grammar: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
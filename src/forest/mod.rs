@@ -0,0 +1,538 @@
+//! Shared packed parse forests of the words of ebnf rules
+//!
+//! The forest is not built from the chart of `cyk` or `earley`, as those only work on the
+//! normal form. Its nonterminals are split into pairs and stripped of unit and null productions,
+//! so trees read off their charts wouldn't follow the rules as they were written. Instead the
+//! rules are desugared like for the LR automata, which keeps one nonterminal per rule, and this
+//! module fills its own chart of the parts of the word each rule and production produces.
+
+use crate::bnf::BnfRule;
+use crate::grammar::{Grammar, NonTerminal, Token};
+use crate::lr;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(test)]
+mod test;
+
+/// What a node of the forest stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Label {
+    /// a rule producing `word[start..end]`
+    Rule {
+        rule: NonTerminal,
+        start: usize,
+        end: usize,
+    },
+    /// the symbols of a production from `dot` on, producing `word[start..end]`
+    Partial {
+        production: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+    },
+    /// the characters `word[start..end]` of a literal
+    Text { start: usize, end: usize },
+}
+
+/// A node of the forest, with all the ways it can be derived
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub label: Label,
+    /// the alternative lists of children, these are the packed nodes of the forest
+    pub families: Vec<Vec<usize>>,
+}
+
+/// A parse tree, in which the helper rules for nested choices and repetitions are left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Rule { name: String, children: Vec<Tree> },
+    Text(String),
+}
+
+/// The number of parse trees of a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Finite(u128),
+    /// at least 2^128 trees, which doesn't fit into a `u128`
+    Saturated,
+    /// a node can be derived from itself
+    Infinite,
+}
+
+/// A shared packed parse forest (SPPF) with all parse trees of a word
+///
+/// Each node stands for a rule or the rest of a production producing a part of the word, and
+/// is shared by all trees that contain it. Productions with more than two symbols are split
+/// into nodes of two children, so the forest only grows polynomially with the word, even if
+/// the number of trees grows exponentially.
+#[derive(Debug, Clone)]
+pub struct Forest {
+    word: Vec<char>,
+    grammar: Grammar,
+    names: Vec<String>,
+    /// number of rules named by the user, the others are helpers
+    named: usize,
+    /// every production as rule and index of its definition
+    productions: Vec<(NonTerminal, usize)>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+    /// whether a family is left out of the trees because it derives a node from itself
+    cycles: Vec<Vec<bool>>,
+    /// number of trees of every node, without the cycles, capped at `u128::MAX`
+    counts: Vec<u128>,
+    /// whether the number of trees of a node doesn't fit into a `u128`
+    saturated: Vec<bool>,
+}
+
+/// Which parts of the word each rule and the rest of each production can produce
+struct Chart {
+    size: usize,
+    rules: Vec<Vec<bool>>,
+    /// by production and dot
+    suffixes: Vec<Vec<Vec<bool>>>,
+}
+
+impl Chart {
+    fn span(&self, start: usize, end: usize) -> usize {
+        start * self.size + end
+    }
+}
+
+impl Forest {
+    /// Builds the forest of all parse trees of `word` by the rule `start`
+    pub fn new(rules: &[BnfRule], start: &str, word: &str) -> Self {
+        let (grammar, names) = lr::desugar(rules);
+        let named = rules.iter().map(|r| &r.name).collect::<HashSet<_>>().len();
+        let productions = grammar
+            .rules
+            .iter()
+            .enumerate()
+            .flat_map(|(nt, rule)| (0..rule.len()).map(move |def| (nt, def)))
+            .collect();
+        let mut forest = Self {
+            word: word.chars().collect(),
+            grammar,
+            names,
+            named,
+            productions,
+            nodes: Vec::new(),
+            root: None,
+            cycles: Vec::new(),
+            counts: Vec::new(),
+            saturated: Vec::new(),
+        };
+
+        let chart = forest.chart();
+        let end = forest.word.len();
+        let start = forest.names[..named].iter().position(|n| n == start);
+        if let Some(rule) = start.filter(|&r| chart.rules[r][chart.span(0, end)]) {
+            let mut memo = HashMap::new();
+            let label = Label::Rule {
+                rule,
+                start: 0,
+                end,
+            };
+            forest.root = Some(forest.node(&chart, label, &mut memo));
+        }
+
+        forest.cycles = forest
+            .nodes
+            .iter()
+            .map(|n| vec![false; n.families.len()])
+            .collect();
+        forest.counts = vec![0; forest.nodes.len()];
+        forest.saturated = vec![false; forest.nodes.len()];
+        if let Some(root) = forest.root {
+            forest.count_trees(root, &mut vec![0; forest.nodes.len()]);
+        }
+        forest
+    }
+
+    fn symbols(&self, production: usize) -> &[Token] {
+        let (rule, def) = self.productions[production];
+        &self.grammar.rules[rule][def]
+    }
+
+    /// whether `symbol` produces `word[start..end]`
+    fn produces(&self, chart: &Chart, symbol: &Token, start: usize, end: usize) -> bool {
+        match symbol {
+            Token::T(t) => self.word[start..end] == t[..],
+            Token::NT(nt) => chart.rules[*nt][chart.span(start, end)],
+        }
+    }
+
+    /// whether the symbol at `dot` produces `word[start..split]` and the ones after it
+    /// `word[split..end]`
+    fn splits(
+        &self,
+        chart: &Chart,
+        (p, dot): (usize, usize),
+        start: usize,
+        split: usize,
+        end: usize,
+    ) -> bool {
+        let symbols = self.symbols(p);
+        if dot + 1 == symbols.len() {
+            split == end && self.produces(chart, &symbols[dot], start, end)
+        } else {
+            self.produces(chart, &symbols[dot], start, split)
+                && chart.suffixes[p][dot + 1][chart.span(split, end)]
+        }
+    }
+
+    /// fills the chart from short to long parts of the word
+    ///
+    /// Rules producing the empty word or a single nonterminal depend on parts of the same
+    /// length, so each part is repeated until nothing changes.
+    fn chart(&self) -> Chart {
+        let size = self.word.len() + 1;
+        let mut chart = Chart {
+            size,
+            rules: vec![vec![false; size * size]; self.grammar.rules.len()],
+            suffixes: (0..self.productions.len())
+                .map(|p| vec![vec![false; size * size]; self.symbols(p).len()])
+                .collect(),
+        };
+        for len in 0..size {
+            for start in 0..size - len {
+                let end = start + len;
+                let span = chart.span(start, end);
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for (p, &(rule, _)) in self.productions.iter().enumerate() {
+                        let symbols = self.symbols(p);
+                        for dot in (0..symbols.len()).rev() {
+                            if !chart.suffixes[p][dot][span]
+                                && (start..=end)
+                                    .any(|split| self.splits(&chart, (p, dot), start, split, end))
+                            {
+                                chart.suffixes[p][dot][span] = true;
+                                changed = true;
+                            }
+                        }
+                        let produces = match symbols.len() {
+                            0 => len == 0,
+                            _ => chart.suffixes[p][0][span],
+                        };
+                        if produces && !chart.rules[rule][span] {
+                            chart.rules[rule][span] = true;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        chart
+    }
+
+    /// adds the node with its descendants, unless it already exists
+    fn node(&mut self, chart: &Chart, label: Label, memo: &mut HashMap<Label, usize>) -> usize {
+        if let Some(&id) = memo.get(&label) {
+            return id;
+        }
+        let id = self.nodes.len();
+        memo.insert(label, id);
+        self.nodes.push(Node {
+            label,
+            families: Vec::new(),
+        });
+
+        let families = match label {
+            Label::Rule { rule, start, end } => {
+                let mut families = Vec::new();
+                for p in 0..self.productions.len() {
+                    if self.productions[p].0 != rule {
+                        continue;
+                    }
+                    if self.symbols(p).is_empty() {
+                        if start == end {
+                            families.push(Vec::new());
+                        }
+                    } else if chart.suffixes[p][0][chart.span(start, end)] {
+                        families.extend(self.families(chart, (p, 0), start, end, memo));
+                    }
+                }
+                families
+            }
+            Label::Partial {
+                production,
+                dot,
+                start,
+                end,
+            } => self.families(chart, (production, dot), start, end, memo),
+            Label::Text { .. } => Vec::new(),
+        };
+        self.nodes[id].families = families;
+        id
+    }
+
+    /// the ways the symbols of a production from `dot` on produce `word[start..end]`
+    fn families(
+        &mut self,
+        chart: &Chart,
+        (p, dot): (usize, usize),
+        start: usize,
+        end: usize,
+        memo: &mut HashMap<Label, usize>,
+    ) -> Vec<Vec<usize>> {
+        let len = self.symbols(p).len();
+        let mut families = Vec::new();
+        for split in start..=end {
+            if !self.splits(chart, (p, dot), start, split, end) {
+                continue;
+            }
+            let first = self.symbol(chart, (p, dot), start, split, memo);
+            let family = if dot + 1 == len {
+                vec![first]
+            } else if dot + 2 == len {
+                vec![first, self.symbol(chart, (p, dot + 1), split, end, memo)]
+            } else {
+                let label = Label::Partial {
+                    production: p,
+                    dot: dot + 1,
+                    start: split,
+                    end,
+                };
+                vec![first, self.node(chart, label, memo)]
+            };
+            families.push(family);
+        }
+        families
+    }
+
+    /// the node of the symbol at `dot` of a production, producing `word[start..end]`
+    fn symbol(
+        &mut self,
+        chart: &Chart,
+        (p, dot): (usize, usize),
+        start: usize,
+        end: usize,
+        memo: &mut HashMap<Label, usize>,
+    ) -> usize {
+        let label = match self.symbols(p)[dot] {
+            Token::T(_) => Label::Text { start, end },
+            Token::NT(rule) => Label::Rule { rule, start, end },
+        };
+        self.node(chart, label, memo)
+    }
+
+    /// counts the trees of every node below `id` with a depth first search, leaving out the
+    /// families that lead back to a node on the current path
+    fn count_trees(&mut self, id: usize, state: &mut Vec<u8>) {
+        const VISITING: u8 = 1;
+        const DONE: u8 = 2;
+        state[id] = VISITING;
+        let mut total: Option<u128> = match self.nodes[id].label {
+            Label::Text { .. } => Some(1),
+            _ => Some(0),
+        };
+        for f in 0..self.nodes[id].families.len() {
+            // `None` once the product overflows, unless a child without trees makes it zero
+            let mut product = Some(1u128);
+            let mut empty = false;
+            for c in 0..self.nodes[id].families[f].len() {
+                let child = self.nodes[id].families[f][c];
+                match state[child] {
+                    VISITING => self.cycles[id][f] = true,
+                    DONE => {}
+                    _ => self.count_trees(child, state),
+                }
+                empty |= self.counts[child] == 0;
+                product = product
+                    .filter(|_| !self.saturated[child])
+                    .and_then(|p| p.checked_mul(self.counts[child]));
+            }
+            if empty {
+                product = Some(0);
+            }
+            if !self.cycles[id][f] {
+                total = total.zip(product).and_then(|(t, p)| t.checked_add(p));
+            }
+        }
+        state[id] = DONE;
+        self.counts[id] = total.unwrap_or(u128::MAX);
+        self.saturated[id] = total.is_none();
+    }
+
+    /// Whether the word is produced by the start rule
+    pub fn accepts(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Whether a node can be derived from itself, i.e. there are infinitely many trees
+    pub fn is_cyclic(&self) -> bool {
+        self.cycles.iter().flatten().any(|&c| c)
+    }
+
+    /// Number of parse trees
+    pub fn count(&self) -> Count {
+        match self.root {
+            _ if self.is_cyclic() => Count::Infinite,
+            Some(root) if self.saturated[root] => Count::Saturated,
+            Some(root) => Count::Finite(self.counts[root]),
+            None => Count::Finite(0),
+        }
+    }
+
+    /// Iterates over the parse trees
+    ///
+    /// If there are infinitely many, only the ones that don't derive a node from itself are
+    /// listed, as far as a depth first search finds such cycles.
+    pub fn trees(&self) -> impl Iterator<Item = Tree> + '_ {
+        let count = self.root.map_or(0, |root| self.counts[root]);
+        (0..count).filter_map(move |index| self.tree(self.root?, index).pop())
+    }
+
+    /// decodes the tree with the given index below a node, helper rules and partial
+    /// productions are replaced by their children
+    fn tree(&self, id: usize, mut index: u128) -> Vec<Tree> {
+        let node = &self.nodes[id];
+        if let Label::Text { start, end } = node.label {
+            return vec![Tree::Text(self.word[start..end].iter().collect())];
+        }
+
+        let mut children = Vec::new();
+        for (f, family) in node.families.iter().enumerate() {
+            if self.cycles[id][f] {
+                continue;
+            }
+            let count = family
+                .iter()
+                .fold(1u128, |n, &c| n.saturating_mul(self.counts[c]));
+            if index >= count {
+                index -= count;
+                continue;
+            }
+            for &child in family.iter().rev() {
+                let n = self.counts[child];
+                children.splice(0..0, self.tree(child, index % n));
+                index /= n;
+            }
+            break;
+        }
+
+        match node.label {
+            Label::Rule { rule, .. } if rule < self.named => {
+                // adjacent characters are shown as one text
+                let mut merged: Vec<Tree> = Vec::new();
+                for child in children {
+                    match (merged.last_mut(), child) {
+                        (Some(Tree::Text(last)), Tree::Text(text)) => last.push_str(&text),
+                        (_, child) => merged.push(child),
+                    }
+                }
+                vec![Tree::Rule {
+                    name: self.names[rule].clone(),
+                    children: merged,
+                }]
+            }
+            _ => children,
+        }
+    }
+
+    pub fn format_symbol(&self, symbol: &Token) -> String {
+        match symbol {
+            Token::T(t) => format!("{:?}", t.iter().collect::<String>()),
+            Token::NT(nt) => format!("<{}>", self.names[*nt]),
+        }
+    }
+
+    /// formats a label with the part of the word it produces
+    pub fn format_label(&self, label: &Label) -> String {
+        match *label {
+            Label::Rule { rule, start, end } => {
+                format!("<{}> {}..{}", self.names[rule], start, end)
+            }
+            Label::Partial {
+                production,
+                dot,
+                start,
+                end,
+            } => {
+                let mut out = format!("<{}> ->", self.names[self.productions[production].0]);
+                for (i, symbol) in self.symbols(production).iter().enumerate() {
+                    if i == dot {
+                        out.push_str(" ·");
+                    }
+                    out.push(' ');
+                    out.push_str(&self.format_symbol(symbol));
+                }
+                format!("{} {}..{}", out, start, end)
+            }
+            Label::Text { start, end } => {
+                format!("{:?}", self.word[start..end].iter().collect::<String>())
+            }
+        }
+    }
+
+    /// Exports the forest in the DOT language of [graphviz](https://graphviz.org)
+    ///
+    /// Rules are drawn as ellipses, partial productions as boxes and literals as plain text.
+    /// A node with more than one family gets a point for each of them.
+    pub fn to_dot(&self) -> String {
+        let escape = |s: String| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut out = String::from("digraph forest {\n");
+        for (id, node) in self.nodes.iter().enumerate() {
+            let shape = match node.label {
+                Label::Rule { .. } => "ellipse",
+                Label::Partial { .. } => "box",
+                Label::Text { .. } => "plaintext",
+            };
+            let label = escape(self.format_label(&node.label));
+            out.push_str(&format!(
+                "    n{} [label=\"{}\", shape={}];\n",
+                id, label, shape
+            ));
+            if let [family] = &node.families[..] {
+                for child in family.iter() {
+                    out.push_str(&format!("    n{} -> n{};\n", id, child));
+                }
+                continue;
+            }
+            for (f, family) in node.families.iter().enumerate() {
+                out.push_str(&format!("    p{}_{} [label=\"\", shape=point];\n", id, f));
+                out.push_str(&format!("    n{} -> p{}_{};\n", id, id, f));
+                for child in family.iter() {
+                    out.push_str(&format!("    p{}_{} -> n{};\n", id, f, child));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Prints the tree with one node per line, indented by its depth
+impl Display for Tree {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fn write(tree: &Tree, f: &mut Formatter, prefix: &str, last: Option<bool>) -> fmt::Result {
+            let (branch, indent) = match last {
+                None => ("", ""),
+                Some(false) => ("├─ ", "│  "),
+                Some(true) => ("└─ ", "   "),
+            };
+            match tree {
+                Tree::Text(text) => writeln!(f, "{}{}{:?}", prefix, branch, text),
+                Tree::Rule { name, children } => {
+                    writeln!(f, "{}{}<{}>", prefix, branch, name)?;
+                    let prefix = format!("{}{}", prefix, indent);
+                    for (i, child) in children.iter().enumerate() {
+                        write(child, f, &prefix, Some(i + 1 == children.len()))?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+        write(self, f, "", None)
+    }
+}
@@ -0,0 +1,127 @@
+use super::*;
+use crate::bnf;
+
+fn forest(source: &str, word: &str) -> Forest {
+    let rules = bnf::from_source(source).unwrap();
+    Forest::new(&rules, &rules[0].name, word)
+}
+
+fn text(s: &str) -> Tree {
+    Tree::Text(s.to_string())
+}
+
+fn rule(name: &str, children: Vec<Tree>) -> Tree {
+    Tree::Rule {
+        name: name.to_string(),
+        children,
+    }
+}
+
+#[test]
+fn unambiguous() {
+    let f = forest("<s> <= a <t> c\n<t> <= b | bb", "abbc");
+    assert!(f.accepts());
+    assert_eq!(f.count(), Count::Finite(1));
+    let trees = f.trees().collect::<Vec<_>>();
+    assert_eq!(
+        trees,
+        vec![rule(
+            "s",
+            vec![text("a"), rule("t", vec![text("bb")]), text("c")]
+        )]
+    );
+}
+
+#[test]
+fn rejected() {
+    let f = forest("<s> <= a <t> c\n<t> <= b | bb", "abbbc");
+    assert!(!f.accepts());
+    assert_eq!(f.count(), Count::Finite(0));
+    assert_eq!(f.trees().count(), 0);
+    assert!(f.nodes().is_empty());
+}
+
+#[test]
+fn catalan() {
+    // the number of ways to put brackets into a sum of n + 1 terms
+    let catalan = [1, 1, 2, 5, 14, 42, 132, 429];
+    for (n, &count) in catalan.iter().enumerate() {
+        let word = vec!["x"; n + 1].join("+");
+        let f = forest("<e> <= <e> + <e> | x", &word);
+        assert_eq!(f.count(), Count::Finite(count), "{}", word);
+        let trees = f.trees().collect::<Vec<_>>();
+        assert_eq!(trees.len() as u128, count);
+        for (i, tree) in trees.iter().enumerate() {
+            assert!(!trees[..i].contains(tree), "duplicate tree for {}", word);
+        }
+    }
+    // the forest stays small even though the number of trees explodes
+    let word = vec!["x"; 60].join("+");
+    let f = forest("<e> <= <e> + <e> | x", &word);
+    assert!(matches!(f.count(), Count::Finite(n) if n > 1 << 100));
+    assert!(f.nodes().len() < 20_000);
+}
+
+#[test]
+fn saturated_count() {
+    // there are about 2^148 ways to put brackets into a sum of 80 terms
+    let word = vec!["x"; 80].join("+");
+    let f = forest("<e> <= <e> + <e> | x", &word);
+    assert_eq!(f.count(), Count::Saturated);
+    assert_eq!(f.trees().take(3).count(), 3);
+}
+
+#[test]
+fn helpers_are_inlined() {
+    let f = forest("<list> <= <item> {, <item>}\n<item> <= a | b", "a,b,a");
+    let trees = f.trees().collect::<Vec<_>>();
+    let item = |s| rule("item", vec![text(s)]);
+    let expected = rule(
+        "list",
+        vec![item("a"), text(","), item("b"), text(","), item("a")],
+    );
+    assert_eq!(trees, vec![expected]);
+}
+
+#[test]
+fn ambiguous_repetitions() {
+    // `aa` is one `a` twice or `aa` once, and `aaa` can be split in three ways
+    let f = forest("<s> <= {a | aa}", "aaa");
+    assert_eq!(f.count(), Count::Finite(3));
+    let f = forest("<s> <= {a} {a}", "aa");
+    assert_eq!(f.count(), Count::Finite(3));
+}
+
+#[test]
+fn cycles() {
+    let f = forest("<s> <= <s> | a", "a");
+    assert!(f.accepts() && f.is_cyclic());
+    assert_eq!(f.count(), Count::Infinite);
+    let trees = f.trees().collect::<Vec<_>>();
+    assert_eq!(trees, vec![rule("s", vec![text("a")])]);
+
+    let f = forest("<s> <= {[a]}", "a");
+    assert_eq!(f.count(), Count::Infinite);
+    assert!(f.trees().count() >= 1);
+}
+
+#[test]
+fn empty_word() {
+    let f = forest("<s> <= [a] [b]", "");
+    assert_eq!(f.count(), Count::Finite(1));
+    assert_eq!(f.trees().collect::<Vec<_>>(), vec![rule("s", vec![])]);
+}
+
+#[test]
+fn display_and_dot() {
+    let f = forest("<e> <= <e> + <e> | x", "x+x+x");
+    let tree = f.trees().next().unwrap();
+    let expected = "<e>\n├─ <e>\n│  └─ \"x\"\n├─ \"+\"\n└─ <e>\n   ├─ <e>\n   │  └─ \"x\"\n   ├─ \"+\"\n   └─ <e>\n      └─ \"x\"\n";
+    assert_eq!(tree.to_string(), expected);
+
+    let dot = f.to_dot();
+    assert!(dot.starts_with("digraph forest {\n") && dot.ends_with("}\n"));
+    assert!(dot.contains("[label=\"<e> 0..5\", shape=ellipse]"));
+    assert!(dot.contains("shape=point"));
+    assert!(dot.contains("\\\"+\\\""));
+}
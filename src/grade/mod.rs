@@ -0,0 +1,159 @@
+use crate::chomsky::Terminal;
+use crate::compare::Comparison;
+use crate::error::Error;
+use crate::json::Json;
+
+#[cfg(test)]
+mod test;
+
+/// Outcome of loading a submission
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    /// the submission could be compared with the reference
+    Ok,
+    /// the file couldn't be read
+    Unreadable,
+    /// the file contains the given number of syntax errors
+    SyntaxErrors(usize),
+    /// the file doesn't contain the rule that should be graded
+    MissingRule,
+    /// the grammar couldn't be brought into normal form
+    InternalError,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Unreadable => "unreadable",
+            Self::SyntaxErrors(_) => "syntax-errors",
+            Self::MissingRule => "missing-rule",
+            Self::InternalError => "internal-error",
+        }
+    }
+}
+
+impl From<&Error> for Status {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Io { .. } | Error::InvalidCompiled(_) => Self::Unreadable,
+            Error::Syntax(errors) => Self::SyntaxErrors(errors.len()),
+            Error::Directives(errors) => Self::SyntaxErrors(errors.len()),
+            Error::NoRules | Error::UnknownRule { .. } => Self::MissingRule,
+            Error::Normalization(_) => Self::InternalError,
+        }
+    }
+}
+
+/// Result of grading a single submission against a reference grammar
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Report {
+    /// name of the submission, usually the file name
+    pub name: String,
+    pub status: Status,
+    /// words accepted by the submission, but not by the reference
+    pub false_accepts: Vec<Terminal>,
+    /// words accepted by the reference, but not by the submission
+    pub false_rejects: Vec<Terminal>,
+    /// number of words both grammars agree on
+    pub correct: usize,
+}
+
+impl Report {
+    /// builds a report from the comparison of the reference (first) and the submission (second)
+    ///
+    /// Words longer than `max_length` are ignored.
+    pub fn from_comparison(name: &str, comparison: &Comparison, max_length: Option<usize>) -> Self {
+        let max_length = max_length.unwrap_or(usize::MAX);
+        let short = |words: &[Terminal]| {
+            words
+                .iter()
+                .filter(|w| w.len() <= max_length)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        Self {
+            name: name.to_string(),
+            status: Status::Ok,
+            false_accepts: short(&comparison.second),
+            false_rejects: short(&comparison.first),
+            correct: short(&comparison.both).len(),
+        }
+    }
+
+    /// builds a report for a submission that couldn't be compared
+    pub fn failed(name: &str, status: Status) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            false_accepts: Vec::new(),
+            false_rejects: Vec::new(),
+            correct: 0,
+        }
+    }
+
+    pub fn syntax_errors(&self) -> usize {
+        match self.status {
+            Status::SyntaxErrors(n) => n,
+            _ => 0,
+        }
+    }
+
+    /// fraction of the checked words on which the submission agrees with the reference
+    ///
+    /// Submissions that couldn't be compared get a score of zero.
+    pub fn score(&self) -> f64 {
+        if self.status != Status::Ok {
+            return 0.0;
+        }
+        let checked = self.correct + self.false_accepts.len() + self.false_rejects.len();
+        if checked == 0 {
+            1.0
+        } else {
+            self.correct as f64 / checked as f64
+        }
+    }
+
+    pub fn csv_header() -> &'static str {
+        "submission,status,score,false_accepts,false_rejects,syntax_errors"
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{:.3},{},{},{}",
+            csv_field(&self.name),
+            self.status.name(),
+            self.score(),
+            self.false_accepts.len(),
+            self.false_rejects.len(),
+            self.syntax_errors()
+        )
+    }
+
+    pub fn to_json(&self) -> Json {
+        let words = |words: &[Terminal]| {
+            words
+                .iter()
+                .map(|w| w.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .into()
+        };
+        Json::object(vec![
+            ("submission", self.name.as_str().into()),
+            ("status", self.status.name().into()),
+            ("score", self.score().into()),
+            ("false_accepts", words(&self.false_accepts)),
+            ("false_rejects", words(&self.false_rejects)),
+            ("syntax_errors", self.syntax_errors().into()),
+        ])
+    }
+}
+
+/// quotes a csv field if necessary
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,88 @@
+use super::*;
+
+fn words(words: &[&str]) -> Vec<Terminal> {
+    words.iter().map(|w| w.chars().collect()).collect()
+}
+
+fn comparison() -> Comparison {
+    Comparison {
+        first: words(&["b", "bbbb"]),
+        second: words(&["aaaaa"]),
+        both: words(&["", "a", "aa", "aaa", "aaaa", "aabb", "abab"]),
+    }
+}
+
+#[test]
+fn scores() {
+    let report = Report::from_comparison("s", &comparison(), None);
+    assert_eq!(report.correct, 7);
+    assert_eq!(report.score(), 0.7);
+
+    // nothing checked, so nothing wrong
+    let empty = Report::from_comparison("s", &Comparison::default(), None);
+    assert_eq!(empty.score(), 1.0);
+
+    let failed = Report::failed("s", Status::SyntaxErrors(3));
+    assert_eq!(failed.score(), 0.0);
+    assert_eq!(failed.syntax_errors(), 3);
+}
+
+#[test]
+fn max_length() {
+    let report = Report::from_comparison("s", &comparison(), Some(3));
+    assert_eq!(report.false_accepts, words(&[]));
+    assert_eq!(report.false_rejects, words(&["b"]));
+    assert_eq!(report.correct, 4);
+    assert_eq!(report.score(), 0.8);
+
+    let report = Report::from_comparison("s", &comparison(), Some(4));
+    assert_eq!(report.false_rejects, words(&["b", "bbbb"]));
+    assert_eq!(report.correct, 7);
+}
+
+#[test]
+fn csv() {
+    assert_eq!(csv_field("plain name"), "plain name");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+
+    let report = Report::from_comparison("alice, bob", &comparison(), None);
+    assert_eq!(report.to_csv(), "\"alice, bob\",ok,0.700,1,2,0");
+    let failed = Report::failed("eve", Status::SyntaxErrors(2));
+    assert_eq!(failed.to_csv(), "eve,syntax-errors,0.000,0,0,2");
+    assert_eq!(Report::csv_header().split(',').count(), 6);
+}
+
+#[test]
+fn json() {
+    let report = Report::from_comparison("s", &comparison(), Some(3));
+    assert_eq!(
+        report.to_json().to_string(),
+        r#"{"submission":"s","status":"ok","score":0.8,"false_accepts":[],"false_rejects":["b"],"syntax_errors":0}"#
+    );
+    let failed = Report::failed("t", Status::MissingRule);
+    assert_eq!(
+        failed.to_json().to_string(),
+        r#"{"submission":"t","status":"missing-rule","score":0,"false_accepts":[],"false_rejects":[],"syntax_errors":0}"#
+    );
+}
+
+#[test]
+fn statuses() {
+    use crate::chomsky::{Grammar, Options};
+    let status = |source: &str, start: Option<&str>| {
+        let options = Options {
+            start: start.map(String::from),
+        };
+        match Grammar::from_source(source, &options) {
+            Ok(_) => Status::Ok,
+            Err(err) => Status::from(&err),
+        }
+    };
+    assert_eq!(status("<s> <= a", None), Status::Ok);
+    assert_eq!(status("<s> <= (a\n<t> <= ]", None), Status::SyntaxErrors(2));
+    assert_eq!(status("", None), Status::MissingRule);
+    assert_eq!(status("<s> <= a", Some("t")), Status::MissingRule);
+}
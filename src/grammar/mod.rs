@@ -1,4 +1,5 @@
 use crate::{debug, debugln};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display, Formatter};
 
 pub type NonTerminal = usize;
@@ -8,7 +9,8 @@ pub type TerminalRef<'a> = &'a [char];
 #[cfg(test)]
 mod test;
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     NT(NonTerminal),
     T(Terminal),
@@ -41,6 +43,7 @@ pub type Definition = Vec<Token>;
 pub type Rule = Vec<Definition>;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grammar {
     pub start: usize,
     pub rules: Vec<Rule>,
@@ -103,16 +106,7 @@ impl Grammar {
                 debugln!("[done]");
             )*};
         }
-        call!(
-            simplify,
-            n_start,
-            n_term,
-            n_bin,
-            n_del,
-            remove_cycles,
-            n_unit,
-            simplify
-        );
+        call!(simplify, n_start, n_term, n_bin, n_del, n_unit, simplify);
         debugln!();
     }
 
@@ -122,8 +116,9 @@ impl Grammar {
         self.start = self.add_rule(new_start)
     }
 
-    /// puts every terminal into its own definition
+    /// puts every terminal into its own definition, with one rule for every distinct terminal
     fn n_term(&mut self) {
+        let mut terms = HashMap::new();
         for r in 0..self.rules.len() {
             for d in 0..self.rules[r].len() {
                 if self.rules[r][d].len() < 2 {
@@ -132,8 +127,9 @@ impl Grammar {
                 for t in 0..self.rules[r][d].len() {
                     if let Token::T(term) = &self.rules[r][d][t] {
                         let term = term.clone();
-                        let new_rule = vec![vec![Token::T(term)]];
-                        let new_rule = self.add_rule(new_rule);
+                        let new_rule = *terms
+                            .entry(term.clone())
+                            .or_insert_with(|| self.add_rule(vec![vec![Token::T(term)]]));
                         self.rules[r][d][t] = Token::NT(new_rule);
                     }
                 }
@@ -142,30 +138,37 @@ impl Grammar {
     }
 
     /// breaks up any definition that contains more than two tokens
+    ///
+    /// Definitions that end the same way share the rules for their ends.
     fn n_bin(&mut self) {
+        let mut pairs = HashMap::new();
         for r in 0..self.rules.len() {
             for d in 0..self.rules[r].len() {
                 let len = self.rules[r][d].len();
                 if len > 2 {
-                    let mut snek = self.add_rule(vec![vec![
-                        self.rules[r][d][len - 2].clone(),
-                        self.rules[r][d][len - 1].clone(),
-                    ]]);
+                    let mut pair = |grammar: &mut Self, def: Definition| {
+                        *pairs
+                            .entry(def.clone())
+                            .or_insert_with(|| grammar.add_rule(vec![def]))
+                    };
+                    let def = &self.rules[r][d];
+                    let last = vec![def[len - 2].clone(), def[len - 1].clone()];
+                    let mut snek = pair(self, last);
                     let mut i = len - 2;
                     while i > 1 {
                         i -= 1;
                         let x = self.rules[r][d][i].clone();
-                        snek = self.add_rule(vec![vec![x, Token::NT(snek)]]);
+                        snek = pair(self, vec![x, Token::NT(snek)]);
                     }
                     self.rules[r][d][1] = Token::NT(snek);
-                    self.rules[r][d].resize(2, Token::T(Default::default()));
+                    self.rules[r][d].truncate(2);
                 }
             }
         }
     }
 
-    /// eliminates all null productions from any nonterminal except the start
-    fn n_del(&mut self) {
+    /// returns for every nonterminal whether it can produce the empty string
+    pub fn nullable(&self) -> Vec<bool> {
         // stores whether a nonterminal can produce the empty string
         let mut nullable = vec![false; self.rules.len()];
 
@@ -189,7 +192,7 @@ impl Grammar {
         // first mark trivially nullable nonterminals as nullable
         let mut q = Vec::new();
         for (idx, rule) in self.rules.iter().enumerate() {
-            if rule.contains(&Vec::new()) {
+            if rule.iter().any(|def| def.iter().all(Token::is_empty)) {
                 // there is at least one definition containing no tokens, i.e. that definition
                 // produces the empty string
                 nullable[idx] = true;
@@ -209,7 +212,7 @@ impl Grammar {
             let null = self.rules[idx].iter().any(|def| {
                 def.iter().all(|t| match t {
                     Token::NT(nt) => nullable[*nt],
-                    _ => false,
+                    Token::T(t) => t.is_empty(),
                 })
             });
             if null {
@@ -222,67 +225,97 @@ impl Grammar {
             }
         }
 
-        // after finding all nullable nonterminals, eliminate null productions everywhere except
-        // the start
+        nullable
+    }
+
+    /// eliminates all null productions from any nonterminal except the start
+    ///
+    /// Every definition is added once more for each way of leaving out nullable tokens. After
+    /// `n_bin`, definitions have at most two tokens, so there are at most three such ways.
+    fn n_del(&mut self) {
+        let nullable = self.nullable();
+        let is_nullable = |token: &Token| match token {
+            Token::NT(nt) => nullable[*nt],
+            Token::T(t) => t.is_empty(),
+        };
+
         for (idx, rule) in self.rules.iter_mut().enumerate() {
-            let mut new_defs = Vec::new();
-            for def in rule.iter() {
-                // indices of nullable nonterminals in the definition
-                let mut nulls = Vec::new();
-                for (i, token) in def.iter().enumerate() {
-                    if let Token::NT(nt) = token {
-                        if nullable[*nt] {
-                            nulls.push(i)
-                        }
+            let mut seen = HashSet::new();
+            let mut defs = Vec::new();
+            for def in std::mem::take(rule) {
+                let mut variants: Vec<Definition> = vec![vec![]];
+                for token in def.iter() {
+                    let mut with = variants.clone();
+                    with.iter_mut().for_each(|v| v.push(token.clone()));
+                    if !is_nullable(token) {
+                        variants.clear();
                     }
+                    variants.append(&mut with);
                 }
-
-                // find all possible combinations of leaving out nullable nonterminals and add as
-                // new definitions
-                for k in 0..((1 << nulls.len()) - 1) {
-                    let mut def = def.clone();
-                    for i in (0..nulls.len()).rev() {
-                        if (1 << i) & k == 0 {
-                            def.remove(nulls[i]);
-                        }
+                // remove null productions unless it is the starting rule
+                for def in variants {
+                    if (idx == self.start || !def.is_empty()) && seen.insert(def.clone()) {
+                        defs.push(def);
                     }
-                    new_defs.push(def);
                 }
             }
-
-            // add new rule combinations to the actual rule and remove duplicates
-            rule.append(&mut new_defs);
-            rule.sort();
-            rule.dedup();
-
-            // remove null productions unless it is the starting rule
-            if idx != self.start {
-                rule.retain(|def| !def.is_empty());
-            }
+            *rule = defs;
         }
     }
 
-    /// eliminates all unit productions of the form `A -> B` by adding the definitions of `B` to `A`
+    /// eliminates all unit productions of the form `A -> B`
+    ///
+    /// Every rule gets the definitions of all rules it reaches by unit productions alone,
+    /// including chains and cycles of them. Rules that are only used in unit productions
+    /// become unreachable, so they are left as they are.
     fn n_unit(&mut self) {
-        for r in 0..self.rules.len() {
-            let mut i = 0;
-            let mut removal = Vec::new();
-            while i < self.rules[r].len() {
-                if self.rules[r][i].len() == 1 {
-                    if let Token::NT(nt) = self.rules[r][i][0] {
-                        for rule in self.rules[nt].clone() {
-                            if !self.rules[r].contains(&rule) {
-                                self.rules[r].push(rule)
+        let unit = |def: &Definition| match def[..] {
+            [Token::NT(nt)] => Some(nt),
+            _ => None,
+        };
+
+        // rules that are used in other definitions than unit productions
+        let mut used = vec![false; self.rules.len()];
+        used[self.start] = true;
+        for def in self.rules.iter().flatten() {
+            if unit(def).is_none() {
+                for tok in def.iter() {
+                    if let Token::NT(nt) = tok {
+                        used[*nt] = true;
+                    }
+                }
+            }
+        }
+
+        // the rule that last visited a nonterminal, to avoid a new visited set for every rule
+        let mut visited = vec![None; self.rules.len()];
+        let mut closures = Vec::new();
+        for a in (0..self.rules.len()).filter(|&a| used[a]) {
+            let mut seen = HashSet::new();
+            let mut defs = Vec::new();
+            let mut q = vec![a];
+            visited[a] = Some(a);
+            while let Some(b) = q.pop() {
+                for def in self.rules[b].iter() {
+                    match unit(def) {
+                        Some(nt) if visited[nt] != Some(a) => {
+                            visited[nt] = Some(a);
+                            q.push(nt);
+                        }
+                        Some(_) => {}
+                        None => {
+                            if seen.insert(def) {
+                                defs.push(def.clone());
                             }
                         }
-                        removal.push(i);
                     }
                 }
-                i += 1;
-            }
-            for i in removal.into_iter().rev() {
-                self.rules[r].remove(i);
             }
+            closures.push((a, defs));
+        }
+
+        for (a, defs) in closures {
+            self.rules[a] = defs;
         }
     }
 }
@@ -305,31 +338,64 @@ impl Grammar {
         }
     }
 
-    fn flatten_impossible(&mut self) {
-        fn is_possible(rules: &[Rule], visited: &mut [bool], nt: NonTerminal) -> bool {
-            if visited[nt] {
-                return false;
+    /// Finds all nonterminals that produce at least one word
+    ///
+    /// A definition is productive once all of its nonterminals are, so every definition counts
+    /// the occurrences of nonterminals in it that aren't known to be productive yet. This
+    /// takes linear time in the size of the grammar.
+    pub fn productive(&self) -> Vec<bool> {
+        let mut productive = vec![false; self.rules.len()];
+
+        // number of tokens of every definition that aren't known to be productive
+        let mut pending = self
+            .rules
+            .iter()
+            .map(|rule| {
+                rule.iter()
+                    .map(|def| def.iter().filter(|t| !t.is_terminal()).count())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // look up of the definitions the i-th nonterminal occurs in, once per occurrence
+        let mut rev = vec![Vec::new(); self.rules.len()];
+        for (idx, rule) in self.rules.iter().enumerate() {
+            for (d, def) in rule.iter().enumerate() {
+                for tok in def.iter() {
+                    if let Token::NT(nt) = tok {
+                        rev[*nt].push((idx, d));
+                    }
+                }
             }
-            visited[nt] = true;
-            for def in rules[nt].iter() {
-                if def.iter().all(|t| match t {
-                    Token::T(_) => true,
-                    Token::NT(nt) => is_possible(rules, visited, *nt),
-                }) {
-                    visited[nt] = false;
-                    return true;
+        }
+
+        // definitions consisting of terminals only are productive from the start
+        let mut q = Vec::new();
+        for (idx, rule) in pending.iter().enumerate() {
+            if rule.contains(&0) {
+                productive[idx] = true;
+                q.push(idx);
+            }
+        }
+
+        while let Some(nt) = q.pop() {
+            for &(idx, d) in rev[nt].iter() {
+                pending[idx][d] -= 1;
+                if pending[idx][d] == 0 && !productive[idx] {
+                    productive[idx] = true;
+                    q.push(idx);
                 }
             }
-            visited[nt] = false;
-            false
         }
 
-        let possible = (0..self.rules.len())
-            .map(|i| is_possible(&self.rules, &mut vec![false; self.rules.len()], i))
-            .collect::<Vec<_>>();
+        productive
+    }
 
+    /// removes all definitions of nonterminals that don't produce any word
+    fn flatten_impossible(&mut self) {
+        let productive = self.productive();
         for (idx, rule) in self.rules.iter_mut().enumerate() {
-            if !possible[idx] {
+            if !productive[idx] {
                 *rule = vec![];
             }
         }
@@ -337,42 +403,33 @@ impl Grammar {
 
     fn remove_cycles(&mut self) {
         for (rule_idx, rule) in self.rules.iter_mut().enumerate() {
-            *rule = rule
-                .iter()
-                .cloned()
-                .filter(|def| *def != vec![Token::NT(rule_idx)])
-                .collect();
+            rule.retain(|def| def[..] != [Token::NT(rule_idx)]);
         }
     }
 
+    /// merges adjacent terminals of every definition into one
     fn concatenate_strings(&mut self) {
-        for rule in self.rules.iter_mut() {
-            for def in rule.iter_mut() {
-                let mut acc = Vec::default();
-                let mut new_def = Vec::new();
-                for tok in def.iter() {
-                    match tok {
-                        Token::T(term) => {
-                            acc.append(&mut term.clone());
-                        }
-                        nt => {
-                            new_def.push(Token::T(acc));
-                            acc = Default::default();
-                            new_def.push(nt.clone())
-                        }
-                    }
+        for def in self.rules.iter_mut().flatten() {
+            if !def
+                .windows(2)
+                .any(|w| w[0].is_terminal() && w[1].is_terminal())
+            {
+                continue;
+            }
+            let mut new_def: Definition = Vec::with_capacity(def.len());
+            for tok in def.drain(..) {
+                match (new_def.last_mut(), tok) {
+                    (Some(Token::T(acc)), Token::T(mut term)) => acc.append(&mut term),
+                    (_, tok) => new_def.push(tok),
                 }
-                new_def.push(Token::T(acc));
-                *def = new_def
             }
+            *def = new_def
         }
     }
 
     fn remove_empty_strings(&mut self) {
-        for rule in self.rules.iter_mut() {
-            for def in rule.iter_mut() {
-                *def = def.clone().into_iter().filter(|t| !t.is_empty()).collect();
-            }
+        for def in self.rules.iter_mut().flatten() {
+            def.retain(|t| !t.is_empty());
         }
     }
 
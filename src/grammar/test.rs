@@ -121,3 +121,130 @@ fn normalize_optional_alternative() {
         })
     )
 }
+
+#[test]
+fn nullable_nonterminals() {
+    let g = Grammar {
+        start: 0,
+        rules: vec![
+            vec![vec![Token::NT(1), Token::NT(2)]],
+            vec![vec![term("a")], vec![]],
+            vec![vec![Token::NT(1)], vec![Token::NT(3)]],
+            vec![vec![term("b")]],
+        ],
+    };
+    assert_eq!(g.nullable(), vec![true, true, true, false]);
+}
+
+#[test]
+fn shared_terminals() {
+    let mut g = Grammar {
+        start: 0,
+        rules: vec![
+            vec![
+                vec![term("a"), Token::NT(1), term("a")],
+                vec![Token::NT(1), term("a")],
+            ],
+            vec![vec![term("b")]],
+        ],
+    };
+    g.normalize();
+    let terms = g
+        .rules
+        .iter()
+        .flatten()
+        .filter(|def| def[..] == [term("a")])
+        .count();
+    assert_eq!(terms, 1, "{}", g);
+    assert!(crate::chomsky::Grammar::from_normalized(&g).is_ok());
+}
+
+#[test]
+fn many_optional_tokens() {
+    use crate::chomsky::{Grammar as CGram, Options};
+    let letters = ('a'..='z').chain('A'..='Z').collect::<Vec<_>>();
+    let def = letters
+        .iter()
+        .map(|c| format!("[{}]", c))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let g = CGram::from_source(&format!("<s> <= {}", def), &Options::new()).unwrap();
+    // every suffix of the optionals is a rule, which can start with any of its letters
+    let n = letters.len();
+    assert!(g.size().definitions() < 2 * n * n, "{}", g.size());
+    assert!(g.null);
+    assert!(g.accepts("aeiouXYZ"));
+    assert!(g.accepts(&letters.iter().collect::<String>()));
+    assert!(!g.accepts("ba"));
+}
+
+#[test]
+fn productive_nonterminals() {
+    let g = Grammar {
+        start: 0,
+        rules: vec![
+            vec![vec![Token::NT(1)], vec![Token::NT(2), term("a")]],
+            vec![vec![Token::NT(2), Token::NT(1)]],
+            vec![vec![term("b")], vec![Token::NT(3)]],
+            vec![vec![Token::NT(3), Token::NT(3)]],
+            vec![],
+        ],
+    };
+    assert_eq!(g.productive(), vec![true, false, true, false, false]);
+}
+
+#[test]
+fn long_chains() {
+    // `i -> "a" i+1` for a productive chain, and one that ends in a cycle of two rules
+    let n = 20_000;
+    let mut g = Grammar {
+        start: 0,
+        rules: vec![vec![vec![Token::NT(1)], vec![Token::NT(n + 1)]]],
+    };
+    for i in 1..n {
+        g.rules.push(vec![vec![term("a"), Token::NT(i + 1)]]);
+    }
+    g.rules.push(vec![vec![term("b")]]);
+    for i in n + 1..2 * n {
+        g.rules.push(vec![vec![term("a"), Token::NT(i + 1)]]);
+    }
+    g.rules.push(vec![vec![Token::NT(2 * n - 1)]]);
+
+    let productive = g.productive();
+    assert!(productive[..=n].iter().all(|p| *p));
+    assert!(!productive[n + 1..].iter().any(|p| *p));
+
+    // the first rule of the cycle is still referenced, but it is left without definitions
+    g.simplify();
+    assert_eq!(g.rules.len(), n + 2);
+    assert_eq!(g.rules.iter().filter(|rule| rule.is_empty()).count(), 1);
+}
+
+#[test]
+fn normalize_random_grammars() {
+    use crate::chomsky::Grammar as CGram;
+    let mut rng = rand::thread_rng();
+    for _ in 0..2000 {
+        let rules = crate::bnf::differential::random_rules(&mut rng);
+        let text = rules
+            .iter()
+            .map(|rule| format!("<{}> <= {}", rule.name, rule.def))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let result = CGram::from_rules(&rules, None);
+        assert!(result.is_ok(), "{:?}\n{}", result, text);
+    }
+}
+
+#[test]
+fn unit_cycles() {
+    use crate::chomsky::{Grammar as CGram, Options};
+    let source = "<a> <= <b> | x\n<b> <= <c> | y <a>\n<c> <= <a> | <b> | z";
+    for start in ["a", "b", "c"] {
+        let g = CGram::from_source(source, &Options::new().start(start)).unwrap();
+        for word in ["x", "z", "yx", "yyz"] {
+            assert!(g.accepts(word), "{} {}", start, word);
+        }
+        assert!(!g.accepts("y"));
+    }
+}
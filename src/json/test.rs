@@ -0,0 +1,33 @@
+use super::*;
+
+#[test]
+fn escaping() {
+    let s = Json::from("a \"quoted\"\\ word\n\u{1}");
+    assert_eq!(s.to_string(), r#""a \"quoted\"\\ word\n\u0001""#);
+}
+
+#[test]
+fn nested() {
+    let json = Json::object(vec![
+        ("word", "aA".into()),
+        ("accepted", true.into()),
+        ("rule", Json::from(None::<String>)),
+        ("lines", vec![1usize, 2].into()),
+        ("empty", Json::object(vec![])),
+    ]);
+    assert_eq!(
+        json.to_string(),
+        r#"{"word":"aA","accepted":true,"rule":null,"lines":[1,2],"empty":{}}"#
+    );
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(Json::from(42usize).to_string(), "42");
+    assert_eq!(Json::from(0.25).to_string(), "0.25");
+    assert_eq!(Json::from(f64::NAN).to_string(), "null");
+    // above 2^53, not every integer is a float
+    let big = (1u128 << 53) + 1;
+    assert_eq!(Json::from(big).to_string(), "9007199254740993");
+    assert_eq!(Json::from(u128::MAX).to_string(), u128::MAX.to_string());
+}
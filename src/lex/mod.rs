@@ -19,8 +19,8 @@ pub enum Token {
     Newline,
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let s = match self {
             Self::String(s) => s,
             Self::RuleOpen => "<",
@@ -35,7 +35,7 @@ impl ToString for Token {
             Self::Assign => "<=",
             Self::Newline => "\\n",
         };
-        s.to_string()
+        write!(f, "{}", s)
     }
 }
 
@@ -44,18 +44,28 @@ impl Token {
         if let Self::String(_) = self {
             String::from("a literal")
         } else {
-            format!("`{}`", self.to_string())
+            format!("`{}`", self)
         }
     }
 }
 
+/// adds the character at `from..=to` of the source to the literal `acc` that spans `span`
+fn extend(acc: &mut std::string::String, span: &mut Location, ch: char, from: usize, to: usize) {
+    if acc.is_empty() {
+        *span = from..=to;
+    } else {
+        *span = *span.start()..=to;
+    }
+    acc.push(ch);
+}
+
 pub fn lex(i: &str) -> Vec<(Location, Token)> {
     use Token::*;
 
     let mut tokens = Vec::new();
     let chars = i.chars().collect::<Vec<_>>();
     let mut acc = std::string::String::new();
-    let mut acc_begin = 0;
+    let mut acc_span = 0..=0;
     let mut i = 0;
 
     while let Some(ch) = chars.get(i) {
@@ -81,7 +91,7 @@ pub fn lex(i: &str) -> Vec<(Location, Token)> {
             '\\' => {
                 i += 1;
                 if let Some(ch) = chars.get(i) {
-                    acc.push(*ch);
+                    extend(&mut acc, &mut acc_span, *ch, from, i);
                     i += 1;
                 }
                 continue;
@@ -91,22 +101,20 @@ pub fn lex(i: &str) -> Vec<(Location, Token)> {
                 continue;
             }
             a => {
-                acc.push(*a);
+                extend(&mut acc, &mut acc_span, *a, i, i);
                 i += 1;
                 continue;
             }
         };
         if !acc.is_empty() {
-            tokens.push((acc_begin..=i - 1, String(acc)));
-            acc = Default::default();
+            tokens.push((acc_span.clone(), String(std::mem::take(&mut acc))));
         }
-        acc_begin = i + 1;
         tokens.push((from..=i, t));
         i += 1;
     }
 
     if !acc.is_empty() {
-        tokens.push((acc_begin..=i - 1, String(acc)));
+        tokens.push((acc_span, String(acc)));
     }
     tokens
 }
@@ -21,3 +21,16 @@ fn lex_a() {
         ]
     )
 }
+
+#[test]
+fn locations() {
+    let spans = |source| {
+        lex(source)
+            .into_iter()
+            .map(|(l, _)| l.into_inner())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(spans("x<="), vec![(0, 0), (1, 2)]);
+    assert_eq!(spans("  ab c\\)|"), vec![(2, 7), (8, 8)]);
+    assert_eq!(spans("ä <b>"), vec![(0, 0), (2, 2), (3, 3), (4, 4)]);
+}
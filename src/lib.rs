@@ -1,9 +1,25 @@
 pub mod bnf;
 pub mod chomsky;
+pub mod codegen;
 pub mod compare;
+#[cfg(feature = "serde")]
+pub mod compiled;
+pub mod cyk;
+pub mod earley;
+pub mod error;
+pub mod forest;
+pub mod grade;
 pub mod grammar;
+pub mod json;
 pub mod lex;
+pub mod ll1;
 pub mod log;
+pub mod lr;
 pub mod parse;
 pub mod producer;
-pub mod styles;
\ No newline at end of file
+#[cfg(test)]
+pub(crate) mod scratch;
+pub mod styles;
+pub mod testfile;
+pub mod transform;
+pub mod watch;
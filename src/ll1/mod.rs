@@ -0,0 +1,510 @@
+use crate::bnf::{BnfPart, BnfRule};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(test)]
+mod test;
+
+/// A symbol that can follow a rule, `None` stands for the end of the input
+pub type Lookahead = Option<char>;
+
+/// Nullable, FIRST and FOLLOW sets of the named rules of an ebnf grammar
+///
+/// The sets are computed on single characters, as the words of a grammar are sequences of
+/// characters and literals may consist of several characters.
+/// All sets are indexed like `names`, `rule` returns the index of a rule.
+pub struct Analysis {
+    /// rule names, in order of their first definition
+    pub names: Vec<String>,
+    /// definition of every rule, as a choice if a rule is defined several times
+    pub defs: Vec<BnfPart>,
+    /// whether a rule can produce the empty word
+    pub nullable: Vec<bool>,
+    /// characters the words of a rule can start with
+    pub first: Vec<BTreeSet<char>>,
+    /// symbols that can follow a rule in a word of the start rule
+    pub follow: Vec<BTreeSet<Lookahead>>,
+    lookup: HashMap<String, usize>,
+}
+
+/// A violation of the LL(1) property
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Conflict {
+    /// two alternatives of a choice can start with the same symbols
+    FirstFirst {
+        rule: String,
+        alternatives: [BnfPart; 2],
+        symbols: BTreeSet<char>,
+    },
+    /// an alternative (or a repetition) can be empty, and another one can start with a symbol
+    /// that can also follow the choice
+    FirstFollow {
+        rule: String,
+        empty: BnfPart,
+        other: BnfPart,
+        symbols: BTreeSet<Lookahead>,
+        /// whether `other` is the part of a repetition, and `empty` stands for ending it
+        repetition: bool,
+    },
+    /// several alternatives of a choice can be empty
+    Nullable {
+        rule: String,
+        alternatives: Vec<BnfPart>,
+    },
+}
+
+/// formats lookahead symbols, using `$` for the end of the input
+pub fn format_symbols<'a>(symbols: impl IntoIterator<Item = &'a Lookahead>) -> String {
+    symbols
+        .into_iter()
+        .map(|s| match s {
+            Some(c) => format!("{:?}", c),
+            None => String::from("$"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Conflict {
+    /// name of the rule the conflict occurs in
+    pub fn rule(&self) -> &str {
+        match self {
+            Self::FirstFirst { rule, .. }
+            | Self::FirstFollow { rule, .. }
+            | Self::Nullable { rule, .. } => rule,
+        }
+    }
+
+    /// short name of the kind of conflict
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::FirstFirst { .. } => "FIRST/FIRST",
+            Self::FirstFollow { .. } => "FIRST/FOLLOW",
+            Self::Nullable { .. } => "nullable",
+        }
+    }
+
+    /// the alternatives involved in the conflict
+    pub fn alternatives(&self) -> Vec<&BnfPart> {
+        match self {
+            Self::FirstFirst { alternatives, .. } => alternatives.iter().collect(),
+            Self::FirstFollow { empty, other, .. } => vec![empty, other],
+            Self::Nullable { alternatives, .. } => alternatives.iter().collect(),
+        }
+    }
+
+    /// the lookahead symbols for which several alternatives apply
+    pub fn symbols(&self) -> BTreeSet<Lookahead> {
+        match self {
+            Self::FirstFirst { symbols, .. } => symbols.iter().map(|c| Some(*c)).collect(),
+            Self::FirstFollow { symbols, .. } => symbols.clone(),
+            Self::Nullable { .. } => BTreeSet::new(),
+        }
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<{}>: ", self.rule())?;
+        match self {
+            Self::FirstFirst {
+                alternatives: [a, b],
+                ..
+            } => write!(
+                f,
+                "`{}` and `{}` can both start with {}",
+                a,
+                b,
+                format_symbols(&self.symbols())
+            ),
+            Self::FirstFollow {
+                other,
+                repetition: true,
+                ..
+            } => write!(
+                f,
+                "the repetition of `{}` can start with {}, which can also follow it",
+                other,
+                format_symbols(&self.symbols())
+            ),
+            Self::FirstFollow { empty, other, .. } => write!(
+                f,
+                "`{}` can be empty, but `{}` can start with {}, which can also follow it",
+                empty,
+                other,
+                format_symbols(&self.symbols())
+            ),
+            Self::Nullable { alternatives, .. } => {
+                let alternatives = alternatives
+                    .iter()
+                    .map(|a| format!("`{}`", a))
+                    .collect::<Vec<_>>();
+                write!(f, "{} can all be empty", alternatives.join(", "))
+            }
+        }
+    }
+}
+
+impl Analysis {
+    /// analyzes `rules`, where words are checked against the rule named `start`
+    pub fn new(rules: &[BnfRule], start: &str) -> Self {
+        let mut names = Vec::new();
+        let mut defs: Vec<Vec<BnfPart>> = Vec::new();
+        let mut lookup = HashMap::new();
+        for rule in rules.iter() {
+            let idx = *lookup.entry(rule.name.clone()).or_insert_with(|| {
+                names.push(rule.name.clone());
+                defs.push(Vec::new());
+                names.len() - 1
+            });
+            defs[idx].push(rule.def.clone());
+        }
+        let defs = defs
+            .into_iter()
+            .map(|mut d| {
+                if d.len() == 1 {
+                    d.remove(0)
+                } else {
+                    BnfPart::Choice(d)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // nullability of the rules is the same as that of their nonterminals in the grammar
+        let (grammar, nonterminals) = crate::bnf::nonterminals(rules);
+        let nullable = grammar.nullable();
+        let nullable = names
+            .iter()
+            .map(|name| nullable[nonterminals[name]])
+            .collect();
+
+        let len = names.len();
+        let mut analysis = Self {
+            names,
+            defs,
+            nullable,
+            first: vec![BTreeSet::new(); len],
+            follow: vec![BTreeSet::new(); len],
+            lookup,
+        };
+        analysis.compute_first();
+        analysis.compute_follow(start);
+        analysis
+    }
+
+    /// index of the rule named `name`, if it is defined
+    pub fn rule(&self, name: &str) -> Option<usize> {
+        self.lookup.get(name).copied()
+    }
+
+    /// whether `part` can produce the empty word
+    pub fn nullable_part(&self, part: &BnfPart) -> bool {
+        use BnfPart::*;
+        match part {
+            Empty | Repeat(_) => true,
+            Literal(s) => s.is_empty(),
+            Choice(parts) => parts.iter().any(|p| self.nullable_part(p)),
+            Concat(parts) => parts.iter().all(|p| self.nullable_part(p)),
+            Rule(name) => self.rule(name).is_some_and(|r| self.nullable[r]),
+        }
+    }
+
+    /// characters the words of `part` can start with
+    pub fn first_part(&self, part: &BnfPart) -> BTreeSet<char> {
+        use BnfPart::*;
+        match part {
+            Empty => BTreeSet::new(),
+            Literal(s) => s.chars().take(1).collect(),
+            Choice(parts) => parts.iter().flat_map(|p| self.first_part(p)).collect(),
+            Concat(parts) => {
+                let mut first = BTreeSet::new();
+                for p in parts.iter() {
+                    first.extend(self.first_part(p));
+                    if !self.nullable_part(p) {
+                        break;
+                    }
+                }
+                first
+            }
+            Repeat(part) => self.first_part(part),
+            Rule(name) => self
+                .rule(name)
+                .map(|r| self.first[r].clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// symbols that can follow the part if `after` can follow the sequence `parts`
+    fn after(&self, part: &BnfPart, after: &BTreeSet<Lookahead>) -> BTreeSet<Lookahead> {
+        let mut result = self
+            .first_part(part)
+            .into_iter()
+            .map(Some)
+            .collect::<BTreeSet<_>>();
+        if self.nullable_part(part) {
+            result.extend(after.iter().cloned());
+        }
+        result
+    }
+
+    fn compute_first(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for r in 0..self.defs.len() {
+                let first = self.first_part(&self.defs[r]);
+                if first != self.first[r] {
+                    self.first[r] = first;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    fn compute_follow(&mut self, start: &str) {
+        if let Some(start) = self.rule(start) {
+            self.follow[start].insert(None);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let mut follow = self.follow.clone();
+            for r in 0..self.defs.len() {
+                self.add_follow(&self.defs[r], &self.follow[r], &mut follow);
+            }
+            if follow != self.follow {
+                self.follow = follow;
+                changed = true;
+            }
+        }
+    }
+
+    fn add_follow(
+        &self,
+        part: &BnfPart,
+        after: &BTreeSet<Lookahead>,
+        follow: &mut [BTreeSet<Lookahead>],
+    ) {
+        use BnfPart::*;
+        match part {
+            Rule(name) => {
+                if let Some(r) = self.rule(name) {
+                    follow[r].extend(after.iter().cloned());
+                }
+            }
+            Choice(parts) => {
+                for p in parts.iter() {
+                    self.add_follow(p, after, follow);
+                }
+            }
+            Concat(parts) => {
+                let mut after = after.clone();
+                for p in parts.iter().rev() {
+                    self.add_follow(p, &after, follow);
+                    after = self.after(p, &after);
+                }
+            }
+            Repeat(inner) => {
+                let after = self.after(part, after);
+                self.add_follow(inner, &after, follow);
+            }
+            Empty | Literal(_) => {}
+        }
+    }
+
+    /// all violations of the LL(1) property, ordered by rule
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for r in 0..self.defs.len() {
+            self.find_conflicts(r, &self.defs[r], &self.follow[r], &mut conflicts);
+        }
+        conflicts
+    }
+
+    fn find_conflicts(
+        &self,
+        rule: usize,
+        part: &BnfPart,
+        after: &BTreeSet<Lookahead>,
+        conflicts: &mut Vec<Conflict>,
+    ) {
+        use BnfPart::*;
+        let name = || self.names[rule].clone();
+        match part {
+            Choice(parts) => {
+                let firsts = parts.iter().map(|p| self.first_part(p)).collect::<Vec<_>>();
+                for i in 0..parts.len() {
+                    for j in i + 1..parts.len() {
+                        let symbols = firsts[i]
+                            .intersection(&firsts[j])
+                            .cloned()
+                            .collect::<BTreeSet<_>>();
+                        if !symbols.is_empty() {
+                            conflicts.push(Conflict::FirstFirst {
+                                rule: name(),
+                                alternatives: [parts[i].clone(), parts[j].clone()],
+                                symbols,
+                            });
+                        }
+                    }
+                }
+
+                let nullable = parts
+                    .iter()
+                    .filter(|p| self.nullable_part(p))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if nullable.len() > 1 {
+                    conflicts.push(Conflict::Nullable {
+                        rule: name(),
+                        alternatives: nullable.clone(),
+                    });
+                }
+                if let Some(empty) = nullable.first() {
+                    for (p, first) in parts.iter().zip(firsts.iter()) {
+                        if p == empty {
+                            continue;
+                        }
+                        let symbols = first
+                            .iter()
+                            .map(|c| Some(*c))
+                            .filter(|c| after.contains(c))
+                            .collect::<BTreeSet<_>>();
+                        if !symbols.is_empty() {
+                            conflicts.push(Conflict::FirstFollow {
+                                rule: name(),
+                                empty: empty.clone(),
+                                other: p.clone(),
+                                symbols,
+                                repetition: false,
+                            });
+                        }
+                    }
+                }
+
+                for p in parts.iter() {
+                    self.find_conflicts(rule, p, after, conflicts);
+                }
+            }
+            Concat(parts) => {
+                let mut after = after.clone();
+                for p in parts.iter().rev() {
+                    self.find_conflicts(rule, p, &after, conflicts);
+                    after = self.after(p, &after);
+                }
+            }
+            Repeat(inner) => {
+                if self.nullable_part(inner) {
+                    conflicts.push(Conflict::Nullable {
+                        rule: name(),
+                        alternatives: vec![(**inner).clone(), BnfPart::Empty],
+                    });
+                }
+                let symbols = self
+                    .first_part(inner)
+                    .into_iter()
+                    .map(Some)
+                    .filter(|c| after.contains(c))
+                    .collect::<BTreeSet<_>>();
+                if !symbols.is_empty() {
+                    conflicts.push(Conflict::FirstFollow {
+                        rule: name(),
+                        empty: BnfPart::Empty,
+                        other: (**inner).clone(),
+                        symbols,
+                        repetition: true,
+                    });
+                }
+                let after = self.after(part, after);
+                self.find_conflicts(rule, inner, &after, conflicts);
+            }
+            Empty | Literal(_) | Rule(_) => {}
+        }
+    }
+
+    /// rules that can reach themselves without consuming any input
+    pub fn left_recursive(&self) -> Vec<usize> {
+        self.left_recursion().into_iter().map(|c| c[0]).collect()
+    }
+
+    /// a shortest cycle for every left recursive rule
+    ///
+    /// Every cycle starts with the left recursive rule, followed by the rules that can appear at
+    /// the beginning of the previous one, e.g. `[a, b]` if `<a>` can start with `<b>`, which can
+    /// start with `<a>` again.
+    pub fn left_recursion(&self) -> Vec<Vec<usize>> {
+        // rules that can appear at the very beginning of a rule
+        let left = self
+            .defs
+            .iter()
+            .map(|d| {
+                let mut left = Vec::new();
+                self.left_rules(d, &mut left);
+                left
+            })
+            .collect::<Vec<_>>();
+
+        (0..self.defs.len())
+            .filter_map(|r| {
+                // breadth-first search for the shortest way back to `r`
+                let mut parent = vec![None; self.defs.len()];
+                let mut q = std::collections::VecDeque::new();
+                q.push_back(r);
+                while let Some(current) = q.pop_front() {
+                    for &next in left[current].iter() {
+                        if next == r {
+                            let mut cycle = vec![current];
+                            while let Some(p) = parent[*cycle.last().unwrap()] {
+                                cycle.push(p);
+                            }
+                            cycle.reverse();
+                            return Some(cycle);
+                        }
+                        if parent[next].is_none() && next != r {
+                            parent[next] = Some(current);
+                            q.push_back(next);
+                        }
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// formats a cycle returned by `left_recursion`, e.g. `<a> -> <b> -> <a>`
+    pub fn format_cycle(&self, cycle: &[usize]) -> String {
+        cycle
+            .iter()
+            .chain(cycle.first())
+            .map(|&r| format!("<{}>", self.names[r]))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+
+    fn left_rules(&self, part: &BnfPart, left: &mut Vec<usize>) {
+        use BnfPart::*;
+        match part {
+            Rule(name) => left.extend(self.rule(name)),
+            Choice(parts) => {
+                for p in parts.iter() {
+                    self.left_rules(p, left);
+                }
+            }
+            Concat(parts) => {
+                for p in parts.iter() {
+                    self.left_rules(p, left);
+                    if !self.nullable_part(p) {
+                        break;
+                    }
+                }
+            }
+            Repeat(inner) => self.left_rules(inner, left),
+            Empty | Literal(_) => {}
+        }
+    }
+
+    /// whether a deterministic recursive-descent parser with one symbol of lookahead exists
+    pub fn is_ll1(&self) -> bool {
+        self.conflicts().is_empty() && self.left_recursive().is_empty()
+    }
+}
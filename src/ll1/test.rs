@@ -0,0 +1,116 @@
+use super::*;
+
+fn rules(source: &str) -> Vec<BnfRule> {
+    crate::bnf::from_source(source).unwrap()
+}
+
+fn set(s: &str) -> BTreeSet<char> {
+    s.chars().collect()
+}
+
+#[test]
+fn first_and_nullable() {
+    let r = rules("<s> <= <a> c | d\n<a> <= [a] {b}");
+    let a = Analysis::new(&r, "s");
+    assert_eq!(a.nullable, vec![false, true]);
+    assert_eq!(a.first, vec![set("abcd"), set("ab")]);
+    assert!(a.is_ll1());
+}
+
+#[test]
+fn nullable_rules() {
+    // chains through other rules, empty parts, and undefined rules, which are never nullable
+    let r = rules("<s> <= <a> <b> | x\n<a> <= <b> ()\n<b> <= {b} | <a>\n<c> <= <d> | c <s>");
+    let a = Analysis::new(&r, "s");
+    assert_eq!(a.nullable, vec![true, true, true, false]);
+}
+
+#[test]
+fn follow() {
+    let r = rules("<s> <= <a> c | <a>\n<a> <= a");
+    let a = Analysis::new(&r, "s");
+    assert_eq!(a.follow[0], vec![None].into_iter().collect());
+    assert_eq!(a.follow[1], vec![None, Some('c')].into_iter().collect());
+}
+
+#[test]
+fn conflicts() {
+    let r = rules("<s> <= ab | ac");
+    assert!(matches!(
+        &Analysis::new(&r, "s").conflicts()[..],
+        [Conflict::FirstFirst { symbols, .. }] if *symbols == set("a")
+    ));
+
+    let r = rules("<s> <= {a} a");
+    assert!(matches!(
+        &Analysis::new(&r, "s").conflicts()[..],
+        [Conflict::FirstFollow { .. }]
+    ));
+
+    let r = rules("<s> <= [a] | {b}");
+    assert!(matches!(
+        &Analysis::new(&r, "s").conflicts()[..],
+        [Conflict::Nullable { .. }]
+    ));
+}
+
+#[test]
+fn left_recursion() {
+    let r = rules("<a> <= <b> x\n<b> <= [y] <a>\n<c> <= c <c>");
+    let a = Analysis::new(&r, "a");
+    assert_eq!(a.left_recursive(), vec![0, 1]);
+    assert!(!a.is_ll1());
+}
+
+#[test]
+fn conflict_display() {
+    let r = rules("<s> <= a<t> | ab\n<t> <= [c] | {d}");
+    let conflicts = Analysis::new(&r, "s")
+        .conflicts()
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        conflicts,
+        vec![
+            "<s>: `a <t>` and `ab` can both start with 'a'",
+            "<t>: `[c]`, `{d}` can all be empty",
+        ]
+    );
+}
+
+/// only repetitions are reported as such, optional parts and empty alternatives are not
+#[test]
+fn first_follow_display() {
+    for (source, message) in [
+        (
+            "<s> <= [a] a",
+            "<s>: `()` can be empty, but `a` can start with 'a', which can also follow it",
+        ),
+        (
+            "<s> <= (a | ()) <t>\n<t> <= a",
+            "<s>: `()` can be empty, but `a` can start with 'a', which can also follow it",
+        ),
+        (
+            "<s> <= {a} a",
+            "<s>: the repetition of `a` can start with 'a', which can also follow it",
+        ),
+    ] {
+        let conflicts = Analysis::new(&rules(source), "s")
+            .conflicts()
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(conflicts, vec![message], "{}", source);
+    }
+}
+
+#[test]
+fn left_recursion_cycles() {
+    let r = rules("<a> <= <b> x | a\n<b> <= <c> | b\n<c> <= <a> | <c>c");
+    let a = Analysis::new(&r, "a");
+    assert_eq!(
+        a.left_recursion(),
+        vec![vec![0, 1, 2], vec![1, 2, 0], vec![2]]
+    );
+}
@@ -0,0 +1,737 @@
+use crate::bnf::{BnfPart, BnfRule};
+use crate::error::Error;
+use crate::grammar::{self, Definition, NonTerminal, Token};
+use crate::ll1::{format_symbols, Lookahead};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(test)]
+mod test;
+
+/// The kind of lookahead used to decide when to reduce
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// reduce regardless of the next symbol
+    Lr0,
+    /// reduce if the next symbol can follow the rule
+    Slr1,
+    /// reduce if the next symbol can follow the rule in the state that is returned to
+    Lalr1,
+}
+
+impl Kind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lr0 => "LR(0)",
+            Self::Slr1 => "SLR(1)",
+            Self::Lalr1 => "LALR(1)",
+        }
+    }
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lr0" => Ok(Self::Lr0),
+            "slr" => Ok(Self::Slr1),
+            "lalr" => Ok(Self::Lalr1),
+            other => Err(format!("unknown kind of automaton `{}`", other)),
+        }
+    }
+}
+
+/// Converts ebnf rules to a grammar without repetitions, choices or literals longer than one character
+///
+/// Returns the grammar together with a name for every nonterminal. The named rules come first,
+/// nested choices and repetitions become helper rules named after the rule they appear in.
+/// Repetitions are left recursive, which LR parsers handle best.
+pub fn desugar(rules: &[BnfRule]) -> (grammar::Grammar, Vec<String>) {
+    struct Desugar {
+        grammar: grammar::Grammar,
+        names: Vec<String>,
+        lookup: HashMap<String, NonTerminal>,
+        helpers: HashMap<NonTerminal, usize>,
+    }
+
+    impl Desugar {
+        fn rule(&mut self, name: &str) -> NonTerminal {
+            if let Some(&nt) = self.lookup.get(name) {
+                return nt;
+            }
+            self.names.push(name.to_string());
+            self.lookup.insert(name.to_string(), self.names.len() - 1);
+            self.grammar.add_rule(vec![])
+        }
+
+        fn helper(&mut self, owner: NonTerminal, defs: Vec<Definition>) -> NonTerminal {
+            let count = self.helpers.entry(owner).or_insert(0);
+            *count += 1;
+            self.names.push(format!("{}:{}", self.names[owner], count));
+            self.grammar.add_rule(defs)
+        }
+
+        fn alternatives(&mut self, part: &BnfPart, owner: NonTerminal) -> Vec<Definition> {
+            match part {
+                BnfPart::Choice(parts) => parts
+                    .iter()
+                    .flat_map(|p| self.alternatives(p, owner))
+                    .collect(),
+                part => vec![self.sequence(part, owner)],
+            }
+        }
+
+        fn sequence(&mut self, part: &BnfPart, owner: NonTerminal) -> Definition {
+            use BnfPart::*;
+            match part {
+                Empty => vec![],
+                Literal(s) => s.chars().map(|c| Token::T(vec![c])).collect(),
+                Rule(name) => vec![Token::NT(self.rule(name))],
+                Concat(parts) => parts.iter().flat_map(|p| self.sequence(p, owner)).collect(),
+                Choice(_) => {
+                    let defs = self.alternatives(part, owner);
+                    vec![Token::NT(self.helper(owner, defs))]
+                }
+                Repeat(part) => {
+                    let alternatives = self.alternatives(part, owner);
+                    let rep = self.helper(owner, vec![vec![]]);
+                    for mut alt in alternatives {
+                        alt.insert(0, Token::NT(rep));
+                        self.grammar.rules[rep].push(alt);
+                    }
+                    vec![Token::NT(rep)]
+                }
+            }
+        }
+    }
+
+    let mut d = Desugar {
+        grammar: grammar::Grammar::new(),
+        names: Vec::new(),
+        lookup: HashMap::new(),
+        helpers: HashMap::new(),
+    };
+    // named rules get the first nonterminals, in order of their definitions
+    for rule in rules.iter() {
+        d.rule(&rule.name);
+    }
+    for rule in rules.iter() {
+        let nt = d.rule(&rule.name);
+        let defs = d.alternatives(&rule.def, nt);
+        d.grammar.rules[nt].extend(defs);
+    }
+    (d.grammar, d.names)
+}
+
+/// A production with a position in it, called the dot
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Item {
+    /// index into `Automaton::productions`
+    pub production: usize,
+    /// number of symbols of the production before the dot
+    pub dot: usize,
+}
+
+/// A state of the automaton, a set of items
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct State {
+    /// the items that aren't added by the closure come first
+    pub items: Vec<Item>,
+    /// number of kernel items at the start of `items`
+    pub kernel: usize,
+    /// state to go to after reading a terminal or reducing a nonterminal
+    pub transitions: BTreeMap<Token, usize>,
+    /// productions to reduce, with the lookahead symbols to reduce on
+    pub reductions: Vec<(usize, BTreeSet<Lookahead>)>,
+}
+
+/// Several actions that are possible in a state for the same lookahead symbols
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    pub state: usize,
+    pub symbols: BTreeSet<Lookahead>,
+    /// whether a terminal can be shifted
+    pub shift: bool,
+    /// the productions that can be reduced
+    pub productions: Vec<usize>,
+    /// shortest input that leads to the state
+    pub prefix: String,
+}
+
+impl Conflict {
+    pub fn kind(&self) -> &'static str {
+        if self.shift {
+            "shift/reduce"
+        } else {
+            "reduce/reduce"
+        }
+    }
+}
+
+/// The LR automaton of a grammar, augmented by a new start rule
+///
+/// Production 0 is the new start rule, reducing it accepts the input.
+pub struct Automaton {
+    pub kind: Kind,
+    pub grammar: grammar::Grammar,
+    /// name of every nonterminal
+    pub names: Vec<String>,
+    /// every definition of the grammar, as pairs of nonterminal and index of the definition
+    pub productions: Vec<(NonTerminal, usize)>,
+    pub states: Vec<State>,
+    /// productions of every nonterminal
+    by_rule: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+    first: Vec<BTreeSet<char>>,
+}
+
+/// a lookahead, or the marker for lookaheads that are propagated from a kernel item
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Look {
+    Symbol(Lookahead),
+    Propagate,
+}
+
+impl Automaton {
+    /// builds the automaton for the rule named `start`, see `desugar`
+    pub fn from_rules(rules: &[BnfRule], start: &str, kind: Kind) -> Result<Self, Error> {
+        let start = crate::bnf::root(rules, Some(start))?;
+        let (mut grammar, names) = desugar(rules);
+        grammar.start = names
+            .iter()
+            .position(|n| n == start)
+            .expect("every rule has a name");
+        Ok(Self::new(&grammar, &names, kind))
+    }
+
+    /// builds the automaton of `grammar`, where `names` contains a name for every nonterminal
+    ///
+    /// Terminals with several characters are split into one terminal per character.
+    pub fn new(grammar: &grammar::Grammar, names: &[String], kind: Kind) -> Self {
+        let mut names = (0..grammar.rules.len())
+            .map(|nt| names.get(nt).cloned().unwrap_or_else(|| nt.to_string()))
+            .collect::<Vec<_>>();
+        let mut grammar = grammar::Grammar {
+            start: grammar.start,
+            rules: grammar
+                .rules
+                .iter()
+                .map(|rule| {
+                    rule.iter()
+                        .map(|def| {
+                            def.iter()
+                                .flat_map(|t| match t {
+                                    Token::T(t) => t.iter().map(|c| Token::T(vec![*c])).collect(),
+                                    nt => vec![nt.clone()],
+                                })
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect(),
+        };
+        let start = grammar.add_rule(vec![vec![Token::NT(grammar.start)]]);
+        names.push(format!("{}'", names[grammar.start]));
+        grammar.start = start;
+
+        let mut productions = vec![(start, 0)];
+        let mut by_rule = vec![Vec::new(); grammar.rules.len()];
+        by_rule[start].push(0);
+        for (nt, rule) in grammar.rules.iter().enumerate() {
+            if nt == start {
+                continue;
+            }
+            for d in 0..rule.len() {
+                by_rule[nt].push(productions.len());
+                productions.push((nt, d));
+            }
+        }
+
+        let nullable = grammar.nullable();
+        let mut automaton = Self {
+            kind,
+            grammar,
+            names,
+            productions,
+            states: Vec::new(),
+            by_rule,
+            nullable,
+            first: Vec::new(),
+        };
+        automaton.compute_first();
+        automaton.build_states();
+        match kind {
+            Kind::Lr0 => automaton.lr0_reductions(),
+            Kind::Slr1 => automaton.slr_reductions(),
+            Kind::Lalr1 => automaton.lalr_reductions(),
+        }
+        automaton
+    }
+
+    /// the symbols of a production
+    pub fn symbols(&self, production: usize) -> &[Token] {
+        let (nt, d) = self.productions[production];
+        &self.grammar.rules[nt][d]
+    }
+
+    /// the symbol after the dot, if the item isn't complete
+    fn next(&self, item: Item) -> Option<&Token> {
+        self.symbols(item.production).get(item.dot)
+    }
+
+    fn compute_first(&mut self) {
+        self.first = vec![BTreeSet::new(); self.grammar.rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (nt, rule) in self.grammar.rules.iter().enumerate() {
+                for def in rule.iter() {
+                    let (first, _) = self.first_of(def);
+                    if !first.is_subset(&self.first[nt]) {
+                        self.first[nt].extend(first);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// characters a sequence of symbols can start with, and whether it is nullable
+    fn first_of(&self, symbols: &[Token]) -> (BTreeSet<char>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols.iter() {
+            match symbol {
+                Token::T(t) => {
+                    first.insert(t[0]);
+                    return (first, false);
+                }
+                Token::NT(nt) => {
+                    first.extend(self.first[*nt].iter().cloned());
+                    if !self.nullable[*nt] {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
+    fn closure(&self, kernel: &BTreeSet<Item>) -> Vec<Item> {
+        let mut items = kernel.iter().cloned().collect::<Vec<_>>();
+        let mut added = vec![false; self.grammar.rules.len()];
+        let mut i = 0;
+        while i < items.len() {
+            if let Some(Token::NT(nt)) = self.next(items[i]) {
+                if !added[*nt] {
+                    added[*nt] = true;
+                    for &production in self.by_rule[*nt].iter() {
+                        items.push(Item { production, dot: 0 });
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    /// builds the canonical collection of sets of LR(0) items
+    fn build_states(&mut self) {
+        let initial = vec![Item {
+            production: 0,
+            dot: 0,
+        }]
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+        let mut lookup = HashMap::new();
+        let mut kernels = vec![initial.clone()];
+        lookup.insert(initial, 0);
+
+        let mut s = 0;
+        while s < kernels.len() {
+            let items = self.closure(&kernels[s]);
+            let mut gotos = BTreeMap::<Token, BTreeSet<Item>>::new();
+            for &item in items.iter() {
+                if let Some(symbol) = self.next(item) {
+                    gotos.entry(symbol.clone()).or_default().insert(Item {
+                        production: item.production,
+                        dot: item.dot + 1,
+                    });
+                }
+            }
+            let mut transitions = BTreeMap::new();
+            for (symbol, kernel) in gotos {
+                let target = *lookup.entry(kernel.clone()).or_insert_with(|| {
+                    kernels.push(kernel);
+                    kernels.len() - 1
+                });
+                transitions.insert(symbol, target);
+            }
+            self.states.push(State {
+                kernel: kernels[s].len(),
+                items,
+                transitions,
+                reductions: Vec::new(),
+            });
+            s += 1;
+        }
+    }
+
+    /// the reductions of complete items, with lookaheads given by `lookahead`
+    fn set_reductions(&mut self, lookahead: impl Fn(&Self, usize, Item) -> BTreeSet<Lookahead>) {
+        for s in 0..self.states.len() {
+            let reductions = self.states[s]
+                .items
+                .iter()
+                .filter(|&&item| self.next(item).is_none())
+                .map(|&item| {
+                    let symbols = if item.production == 0 {
+                        // accepting is only possible at the end of the input
+                        vec![None].into_iter().collect()
+                    } else {
+                        lookahead(self, s, item)
+                    };
+                    (item.production, symbols)
+                })
+                .collect();
+            self.states[s].reductions = reductions;
+        }
+    }
+
+    fn lr0_reductions(&mut self) {
+        let mut all = self
+            .grammar
+            .rules
+            .iter()
+            .flatten()
+            .flatten()
+            .filter_map(|t| match t {
+                Token::T(t) => Some(Some(t[0])),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        all.insert(None);
+        self.set_reductions(|_, _, _| all.clone());
+    }
+
+    fn slr_reductions(&mut self) {
+        let mut follow = vec![BTreeSet::new(); self.grammar.rules.len()];
+        follow[self.grammar.start].insert(None);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(nt, d) in self.productions.iter() {
+                let def = &self.grammar.rules[nt][d];
+                for (i, symbol) in def.iter().enumerate() {
+                    if let Token::NT(b) = symbol {
+                        let (first, nullable) = self.first_of(&def[i + 1..]);
+                        let mut new = first.into_iter().map(Some).collect::<BTreeSet<_>>();
+                        if nullable {
+                            new.extend(follow[nt].iter().cloned());
+                        }
+                        if !new.is_subset(&follow[*b]) {
+                            follow[*b].extend(new);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        self.set_reductions(|a, _, item| follow[a.productions[item.production].0].clone());
+    }
+
+    /// closure of items with lookaheads, as in the construction of LR(1) automata
+    fn closure1(&self, kernel: Vec<(Item, Look)>) -> BTreeSet<(Item, Look)> {
+        let mut items = BTreeSet::new();
+        let mut q = kernel;
+        while let Some((item, look)) = q.pop() {
+            if !items.insert((item, look)) {
+                continue;
+            }
+            if let Some(Token::NT(nt)) = self.next(item) {
+                let (first, nullable) =
+                    self.first_of(&self.symbols(item.production)[item.dot + 1..]);
+                let mut looks = first
+                    .into_iter()
+                    .map(|c| Look::Symbol(Some(c)))
+                    .collect::<Vec<_>>();
+                if nullable {
+                    looks.push(look);
+                }
+                for &production in self.by_rule[*nt].iter() {
+                    for &look in looks.iter() {
+                        q.push((Item { production, dot: 0 }, look));
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// computes LALR(1) lookaheads by propagating them between kernel items
+    fn lalr_reductions(&mut self) {
+        let mut lookaheads = self
+            .states
+            .iter()
+            .map(|s| {
+                s.items[..s.kernel]
+                    .iter()
+                    .map(|&item| (item, BTreeSet::new()))
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        lookaheads[0]
+            .values_mut()
+            .for_each(|l: &mut BTreeSet<Lookahead>| {
+                l.insert(None);
+            });
+
+        // kernel items that receive all lookaheads of a kernel item
+        let mut propagate = HashMap::<(usize, Item), Vec<(usize, Item)>>::new();
+        for (s, state) in self.states.iter().enumerate() {
+            for &kernel in state.items[..state.kernel].iter() {
+                for (item, look) in self.closure1(vec![(kernel, Look::Propagate)]) {
+                    let symbol = match self.next(item) {
+                        Some(symbol) => symbol,
+                        None => continue,
+                    };
+                    let target = state.transitions[symbol];
+                    let moved = Item {
+                        production: item.production,
+                        dot: item.dot + 1,
+                    };
+                    match look {
+                        Look::Symbol(symbol) => {
+                            lookaheads[target].get_mut(&moved).unwrap().insert(symbol);
+                        }
+                        Look::Propagate => propagate
+                            .entry((s, kernel))
+                            .or_default()
+                            .push((target, moved)),
+                    }
+                }
+            }
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (&(s, item), targets) in propagate.iter() {
+                let from = lookaheads[s][&item].clone();
+                for &(t, target) in targets.iter() {
+                    let to = lookaheads[t].get_mut(&target).unwrap();
+                    if !from.is_subset(to) {
+                        to.extend(from.iter().cloned());
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // complete items that aren't part of the kernel get their lookaheads from the closure
+        let complete = (0..self.states.len())
+            .map(|s| {
+                let kernel = lookaheads[s]
+                    .iter()
+                    .flat_map(|(&item, looks)| looks.iter().map(move |&l| (item, Look::Symbol(l))))
+                    .collect();
+                let mut reductions = BTreeMap::<Item, BTreeSet<Lookahead>>::new();
+                for (item, look) in self.closure1(kernel) {
+                    if let (None, Look::Symbol(l)) = (self.next(item), look) {
+                        reductions.entry(item).or_default().insert(l);
+                    }
+                }
+                reductions
+            })
+            .collect::<Vec<_>>();
+        self.set_reductions(|_, s, item| complete[s].get(&item).cloned().unwrap_or_default());
+    }
+
+    /// all states that have several possible actions for some lookahead
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let prefixes = self.prefixes();
+        let mut conflicts = Vec::new();
+        for (s, state) in self.states.iter().enumerate() {
+            let mut actions = BTreeMap::<Lookahead, (bool, Vec<usize>)>::new();
+            for (production, symbols) in state.reductions.iter() {
+                for &symbol in symbols.iter() {
+                    let shift =
+                        symbol.is_some_and(|c| state.transitions.contains_key(&Token::T(vec![c])));
+                    let action = actions.entry(symbol).or_insert((shift, Vec::new()));
+                    action.1.push(*production);
+                }
+            }
+
+            let mut grouped = BTreeMap::<(bool, Vec<usize>), BTreeSet<Lookahead>>::new();
+            for (symbol, (shift, productions)) in actions {
+                if productions.len() + shift as usize > 1 {
+                    grouped
+                        .entry((shift, productions))
+                        .or_default()
+                        .insert(symbol);
+                }
+            }
+            for ((shift, productions), symbols) in grouped {
+                conflicts.push(Conflict {
+                    state: s,
+                    symbols,
+                    shift,
+                    productions,
+                    prefix: prefixes[s].clone(),
+                });
+            }
+        }
+        conflicts
+    }
+
+    /// shortest input that leads to every state
+    fn prefixes(&self) -> Vec<String> {
+        let words = self.shortest_words();
+        let mut prefixes = vec![None; self.states.len()];
+        prefixes[0] = Some(String::new());
+        let mut q = VecDeque::new();
+        q.push_back(0);
+        while let Some(s) = q.pop_front() {
+            for (symbol, &target) in self.states[s].transitions.iter() {
+                if prefixes[target].is_some() {
+                    continue;
+                }
+                let mut prefix = prefixes[s].clone().unwrap_or_default();
+                match symbol {
+                    Token::T(t) => prefix.extend(t.iter()),
+                    Token::NT(nt) => prefix.push_str(words[*nt].as_deref().unwrap_or("…")),
+                }
+                prefixes[target] = Some(prefix);
+                q.push_back(target);
+            }
+        }
+        prefixes
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    /// a shortest word of every nonterminal, if there is any
+    fn shortest_words(&self) -> Vec<Option<String>> {
+        let mut words: Vec<Option<String>> = vec![None; self.grammar.rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(nt, d) in self.productions.iter() {
+                let word = self.grammar.rules[nt][d]
+                    .iter()
+                    .map(|t| match t {
+                        Token::T(t) => Some(t.iter().collect::<String>()),
+                        Token::NT(nt) => words[*nt].clone(),
+                    })
+                    .collect::<Option<String>>();
+                if let Some(word) = word {
+                    // `Option::is_none_or` would need rust 1.82
+                    #[allow(clippy::unnecessary_map_or)]
+                    let shorter = words[nt].as_ref().map_or(true, |w| w.len() > word.len());
+                    if shorter {
+                        words[nt] = Some(word);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        words
+    }
+
+    pub fn format_symbol(&self, symbol: &Token) -> String {
+        match symbol {
+            Token::T(t) => format!("{:?}", t[0]),
+            Token::NT(nt) => format!("<{}>", self.names[*nt]),
+        }
+    }
+
+    pub fn format_production(&self, production: usize) -> String {
+        self.format_item(Item {
+            production,
+            dot: usize::MAX,
+        })
+    }
+
+    /// formats an item, with `·` marking the dot
+    pub fn format_item(&self, item: Item) -> String {
+        let symbols = self.symbols(item.production);
+        let mut out = format!("<{}> ->", self.names[self.productions[item.production].0]);
+        for (i, symbol) in symbols.iter().enumerate() {
+            if i == item.dot {
+                out.push_str(" ·");
+            }
+            out.push(' ');
+            out.push_str(&self.format_symbol(symbol));
+        }
+        if item.dot == symbols.len() {
+            out.push_str(" ·");
+        } else if symbols.is_empty() {
+            out.push_str(" ε");
+        }
+        out
+    }
+
+    pub fn format_conflict(&self, conflict: &Conflict) -> String {
+        let productions = conflict
+            .productions
+            .iter()
+            .map(|&p| format!("`{}`", self.format_production(p)))
+            .collect::<Vec<_>>()
+            .join(" and ");
+        let action = if conflict.shift {
+            format!("shifting or reducing {}", productions)
+        } else {
+            format!("reducing {}", productions)
+        };
+        format!(
+            "state {}, after `{}` with {} next: {}",
+            conflict.state,
+            conflict.prefix,
+            format_symbols(&conflict.symbols),
+            action
+        )
+    }
+}
+
+/// Lists the states with their items and actions
+impl Display for Automaton {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (s, state) in self.states.iter().enumerate() {
+            if s > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "state {}", s)?;
+            for &item in state.items.iter() {
+                writeln!(f, "    {}", self.format_item(item))?;
+            }
+            writeln!(f)?;
+            for (symbol, target) in state.transitions.iter() {
+                let action = if symbol.is_terminal() {
+                    "shift"
+                } else {
+                    "goto"
+                };
+                writeln!(
+                    f,
+                    "    on {} {} {}",
+                    self.format_symbol(symbol),
+                    action,
+                    target
+                )?;
+            }
+            for (production, symbols) in state.reductions.iter() {
+                if *production == 0 {
+                    writeln!(f, "    on $ accept")?;
+                } else if !symbols.is_empty() {
+                    writeln!(
+                        f,
+                        "    on {} reduce {}",
+                        format_symbols(symbols),
+                        self.format_production(*production)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
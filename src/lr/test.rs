@@ -0,0 +1,79 @@
+use super::*;
+
+fn automaton(source: &str, kind: Kind) -> Automaton {
+    let rules = crate::bnf::from_source(source).unwrap();
+    Automaton::from_rules(&rules, &rules[0].name, kind).unwrap()
+}
+
+#[test]
+fn unknown_start() {
+    // only the repetition of <s> needs a lookahead
+    let rules = crate::bnf::from_source("<s> <= {a}\n<t> <= b").unwrap();
+    let automaton = Automaton::from_rules(&rules, "t", Kind::Lr0).unwrap();
+    assert!(automaton.conflicts().is_empty());
+    assert!(matches!(
+        Automaton::from_rules(&rules, "u", Kind::Lr0),
+        Err(Error::UnknownRule { .. })
+    ));
+}
+
+#[test]
+fn desugar_repeat() {
+    let rules = crate::bnf::from_source("<s> <= {ab | c}").unwrap();
+    let (g, names) = desugar(&rules);
+    let t = |c| Token::T(vec![c]);
+    assert_eq!(names, vec!["s", "s:1"]);
+    assert_eq!(
+        g.rules,
+        vec![
+            vec![vec![Token::NT(1)]],
+            vec![
+                vec![],
+                vec![Token::NT(1), t('a'), t('b')],
+                vec![Token::NT(1), t('c')]
+            ],
+        ]
+    );
+}
+
+#[test]
+fn lr0() {
+    assert!(automaton("<s> <= a<s>b | c", Kind::Lr0)
+        .conflicts()
+        .is_empty());
+    // the end of the repetition needs a lookahead
+    assert_eq!(automaton("<s> <= {a}", Kind::Lr0).conflicts().len(), 1);
+    assert!(automaton("<s> <= {a}", Kind::Slr1).conflicts().is_empty());
+}
+
+#[test]
+fn lalr_but_not_slr() {
+    let source = "<s> <= <l>=<r> | <r>\n<l> <= *<r> | i\n<r> <= <l>";
+    let conflicts = automaton(source, Kind::Slr1).conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert!(conflicts[0].shift);
+    assert_eq!(conflicts[0].symbols, vec![Some('=')].into_iter().collect());
+    assert_eq!(conflicts[0].prefix, "i");
+    assert!(automaton(source, Kind::Lalr1).conflicts().is_empty());
+}
+
+#[test]
+fn ambiguous() {
+    let a = automaton("<e> <= <e>+<e> | a", Kind::Lalr1);
+    let conflicts = a.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].kind(), "shift/reduce");
+    assert_eq!(conflicts[0].prefix, "a+a");
+    assert_eq!(
+        a.format_production(conflicts[0].productions[0]),
+        "<e> -> <e> '+' <e>"
+    );
+}
+
+#[test]
+fn reduce_reduce() {
+    let conflicts = automaton("<s> <= <a> | <b>\n<a> <= x\n<b> <= x", Kind::Lalr1).conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].kind(), "reduce/reduce");
+    assert_eq!(conflicts[0].symbols, vec![None].into_iter().collect());
+}
@@ -1,27 +1,95 @@
-use std::io::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use structopt::StructOpt;
 
+use parsley::error::Error;
+use parsley::json::Json;
+use parsley::watch::{ErrorChange, Verdicts};
 use parsley::*;
 
 #[derive(Debug, StructOpt)]
 struct Options {
     #[structopt(short, long)]
     debug: bool,
+    #[structopt(
+        long,
+        global = true,
+        default_value = "text",
+        possible_values = &["text", "json"],
+        help = "Output format, `json` prints a single json object per run"
+    )]
+    format: Format,
     #[structopt(subcommand)]
     cmd: Command,
 }
 
+// Option to choose the start rule, which isn't a doc comment as structopt would use it as the
+// description of every subcommand that flattens it.
+//
+// Subcommands that work on every rule of a file, like `test` whose directives name their rule,
+// or the rewrites that keep all rules, don't have it.
+#[derive(Debug, StructOpt)]
+struct Start {
+    #[structopt(
+        long,
+        help = "Name of the rule words are checked against, instead of the first rule of the file"
+    )]
+    start: Option<String>,
+}
+
 #[derive(Debug, StructOpt)]
 enum Command {
     #[structopt(about = "Parses the file with the ebnf rule and reports any errors")]
-    Parse { file: PathBuf },
+    Parse {
+        file: PathBuf,
+        #[structopt(flatten)]
+        start: Start,
+        #[structopt(short, long, help = "Re-runs the command whenever the file changes")]
+        watch: bool,
+        #[structopt(long, help = "Reports the size of the grammar in Chomsky normal form")]
+        stats: bool,
+    },
     #[structopt(about = "Checks a word against the rules in the given file")]
-    Check { file: PathBuf, word: String },
+    Check {
+        file: PathBuf,
+        word: String,
+        #[structopt(flatten)]
+        start: Start,
+    },
     #[structopt(
-        about = "Checks words given in a file against the ebnf rules, separated by newline"
+        about = "Checks words given in a file against the ebnf rules, separated by newline",
+        long_about = "Checks words given in a file against the ebnf rules, separated by newline.\n\n\
+                      If every word is prefixed with `+` (must be accepted) or `-` (must be rejected), \
+                      only words with an unexpected verdict are reported and the exit code indicates \
+                      whether all of them matched. Lines starting with `#` are comments."
     )]
-    CheckFile { rules: PathBuf, words: PathBuf },
+    CheckFile {
+        rules: PathBuf,
+        words: PathBuf,
+        #[structopt(flatten)]
+        start: Start,
+        #[structopt(
+            short,
+            long,
+            help = "Reads words prefixed with `+` or `-` as expected verdicts, as for files ending in `.expect`"
+        )]
+        expect: bool,
+        #[structopt(
+            short,
+            long,
+            help = "Re-runs the command whenever one of the files changes"
+        )]
+        watch: bool,
+    },
+    #[structopt(
+        about = "Runs the `@accept <rule> word` and `@reject <rule> word` directives in the file"
+    )]
+    Test {
+        file: PathBuf,
+        #[structopt(short, long, help = "Re-runs the command whenever the file changes")]
+        watch: bool,
+    },
     #[structopt(
         about = "Compares two sets of ebnf rules, and reports any words that are only accepted by either"
     )]
@@ -30,143 +98,1576 @@ enum Command {
         other_file: PathBuf,
         #[structopt(default_value = "1000")]
         limit: usize,
+        #[structopt(long, help = "Name of the rule to compare in the first file")]
+        start1: Option<String>,
+        #[structopt(long, help = "Name of the rule to compare in the second file")]
+        start2: Option<String>,
+        #[structopt(
+            short,
+            long,
+            help = "Re-runs the command whenever one of the files changes"
+        )]
+        watch: bool,
+    },
+    #[structopt(
+        about = "Compares every file in a directory with a reference grammar and grades them"
+    )]
+    Grade {
+        reference: PathBuf,
+        submissions: PathBuf,
+        #[structopt(
+            long,
+            default_value = "1000",
+            help = "Number of words of the reference grammar to compare"
+        )]
+        limit: usize,
+        #[structopt(long, help = "Ignores words longer than this")]
+        max_length: Option<usize>,
+        #[structopt(
+            long,
+            help = "Writes a summary to this file, as json if it ends in `.json`, otherwise as csv"
+        )]
+        summary: Option<PathBuf>,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[cfg(feature = "serde")]
+    #[structopt(
+        about = "Normalizes the grammar and stores it as json, which all other commands accept instead of ebnf rules if the file name ends in `.json`"
+    )]
+    Compile {
+        file: PathBuf,
+        #[structopt(short, long, help = "File to write to, instead of printing the result")]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[structopt(about = "Generates a standalone recognizer for the grammar")]
+    Codegen {
+        file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "rust",
+            possible_values = &["rust"],
+            help = "Language of the generated code"
+        )]
+        lang: String,
+        #[structopt(short, long, help = "File to write to, instead of printing the result")]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[structopt(
+        about = "Prints the nullable, FIRST and FOLLOW sets of every rule and reports LL(1) conflicts"
+    )]
+    Ll1 {
+        file: PathBuf,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[structopt(about = "Prints the states of the LR automaton and reports its conflicts")]
+    Lr {
+        file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "lalr",
+            possible_values = &["lr0", "slr", "lalr"],
+            help = "Kind of lookahead used to decide when to reduce"
+        )]
+        kind: lr::Kind,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[structopt(about = "Prints the parse trees of a word, or its parse forest with --dot")]
+    Forest {
+        file: PathBuf,
+        word: String,
+        #[structopt(long, help = "Prints the whole forest in the DOT format of graphviz")]
+        dot: bool,
+        #[structopt(long, default_value = "10", help = "Number of trees to print at most")]
+        trees: usize,
+        #[structopt(short, long, help = "File to write the DOT graph to")]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        start: Start,
+    },
+    #[structopt(about = "Prints every rule of the file after simplifying it")]
+    Simplify {
+        file: PathBuf,
+        #[structopt(short, long, help = "File to write the simplified rules to")]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        about = "Reports rules that can start with themselves, and rewrites them with --fix"
+    )]
+    LeftRecursion {
+        file: PathBuf,
+        #[structopt(long, help = "Prints the rules without left recursion instead")]
+        fix: bool,
+        #[structopt(short, long, help = "File to write the fixed rules to")]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        about = "Extracts common prefixes of alternatives, e.g. `a b | a c` becomes `a (b | c)`"
+    )]
+    LeftFactor {
+        file: PathBuf,
+        #[structopt(short, long, help = "File to write the factored rules to")]
+        output: Option<PathBuf>,
+        #[structopt(
+            long,
+            help = "Checks that every rule accepts the same words as before, among this many words"
+        )]
+        verify: Option<usize>,
     },
     #[structopt(about = "Gives a list of words produced by the ebnf rules, increasing in length")]
     ProduceWords {
         file: PathBuf,
         #[structopt(default_value = "20")]
         limit: usize,
+        #[structopt(flatten)]
+        start: Start,
     },
 }
 
-fn parse(file: &Path) -> chomsky::Grammar {
-    let path = file.as_os_str().to_string_lossy();
-    let ebnf = read_file(file);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown format `{}`", other)),
+        }
+    }
+}
 
-    let tokens = lex::lex(&ebnf);
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// whether output should be printed as json instead of text
+fn json() -> bool {
+    JSON.load(Ordering::SeqCst)
+}
+
+/// reports an error, with additional fields that are only part of the json output, and returns
+/// the failure with exit code `code`
+fn report_error(code: i32, message: &str, details: Vec<(&str, Json)>) -> Failure {
+    let error = if json() {
+        let mut fields = vec![("error", message.into())];
+        fields.extend(details);
+        let error = Json::object(fields).to_string();
+        println!("{}", error);
+        error
+    } else {
+        println!("{}: {}", styles::ERROR.apply_to("error"), message);
+        message.to_string()
+    };
+    Failure {
+        code,
+        errors: vec![error],
+    }
+}
+
+/// An error that has already been reported to the user
+struct Failure {
+    /// exit code of the program
+    code: i32,
+    /// the reported errors, to tell in watch mode whether they changed
+    errors: Vec<String>,
+}
+
+impl Failure {
+    /// a failure that wasn't caused by errors, like a failing test
+    fn new(code: i32) -> Self {
+        Self {
+            code,
+            errors: Vec::new(),
+        }
+    }
+}
+
+type Outcome<T> = Result<T, Failure>;
+
+/// marker that is printed behind verdicts that changed since the last run
+fn changed_marker(changed: bool) -> String {
+    if changed {
+        format!(" {}", styles::WARNING.apply_to("(changed)"))
+    } else {
+        String::new()
+    }
+}
+
+/// line that is printed after a run whose errors differ from the previous run, or that has the
+/// same errors again
+fn error_change_line(change: ErrorChange) -> String {
+    match change {
+        ErrorChange::New => styles::WARNING.apply_to("(new errors)").to_string(),
+        ErrorChange::Same => styles::INFO.apply_to("(same errors as before)").to_string(),
+        ErrorChange::Different => styles::WARNING.apply_to("(errors changed)").to_string(),
+        ErrorChange::Fixed => styles::GOOD.apply_to("(errors fixed)").to_string(),
+    }
+}
+
+fn parse(file: &Path, start: Option<&str>) -> Outcome<chomsky::Grammar> {
+    let source = read_file(file)?;
+    #[cfg(feature = "serde")]
+    if compiled::is_compiled(file) {
+        return compiled::Compiled::from_json(&source)
+            .and_then(|compiled| compiled.grammar(start))
+            .map_err(|err| report(&err, file, &source));
+    }
+    let options = chomsky::Options {
+        start: start.map(String::from),
+    };
+    chomsky::Grammar::from_source(&source, &options).map_err(|err| report(&err, file, &source))
+}
+
+/// reads the ebnf rules of a file, or the rules of a compiled grammar, reporting any errors
+///
+/// Also returns the source of the file, as it may contain test directives.
+fn parse_rules(file: &Path) -> Outcome<(String, Vec<bnf::BnfRule>)> {
+    let source = read_file(file)?;
+    #[cfg(feature = "serde")]
+    let rules = if compiled::is_compiled(file) {
+        compiled::Compiled::from_json(&source).map(|compiled| compiled.rules)
+    } else {
+        bnf::from_source(&source)
+    };
+    #[cfg(not(feature = "serde"))]
+    let rules = bnf::from_source(&source);
+    match rules {
+        Ok(rules) => Ok((source, rules)),
+        Err(err) => Err(report(&err, file, &source)),
+    }
+}
+
+/// reads the test directives of a rules file, or those stored in a compiled grammar
+#[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+fn directives(file: &Path, source: &str) -> Result<Vec<testfile::Expectation>, Error> {
+    #[cfg(feature = "serde")]
+    if compiled::is_compiled(file) {
+        return compiled::Compiled::from_json(source).map(|compiled| compiled.tests);
+    }
+    testfile::directives(source).map_err(Error::Directives)
+}
 
-    let rules = match parse::parse(&tokens) {
-        Ok(rules) => rules,
-        Err(errs) => {
-            print!("{}", parse::format_errors(&path, &ebnf, errs));
+/// reports an error that occurred while building the grammar of `file`
+fn report(err: &Error, file: &Path, source: &str) -> Failure {
+    let path = file.as_os_str().to_string_lossy();
+    match err {
+        Error::Io { .. } => report_error(2, &err.to_string(), vec![("file", path.as_ref().into())]),
+        Error::Syntax(errs) if json() => {
+            let errors = errs
+                .iter()
+                .map(|e| {
+                    let (line, column) = e.position(source).unzip();
+                    Json::object(vec![
+                        ("line", line.into()),
+                        ("column", column.into()),
+                        (
+                            "expected",
+                            e.expected()
+                                .iter()
+                                .map(|t| match t {
+                                    lex::Token::String(_) => String::from("literal"),
+                                    t => t.to_string(),
+                                })
+                                .collect::<Vec<_>>()
+                                .into(),
+                        ),
+                        ("got", e.got().map(|t| t.to_string()).into()),
+                        ("message", e.message().into()),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            report_error(
+                1,
+                "syntax errors",
+                vec![("file", path.as_ref().into()), ("errors", errors.into())],
+            )
+        }
+        Error::Syntax(errs) => {
+            let errors = parse::format_errors(&path, source, errs.clone());
+            print!("{}", errors);
             println!(
                 "{}: aborting due to previous errors",
                 styles::ERROR.apply_to("error")
             );
-            std::process::exit(1);
+            Failure {
+                code: 1,
+                errors: vec![errors],
+            }
+        }
+        Error::Directives(errs) if json() => {
+            let errors = errs
+                .iter()
+                .map(|e| {
+                    Json::object(vec![
+                        ("line", e.line.into()),
+                        ("message", e.message.as_str().into()),
+                    ])
+                })
+                .collect::<Vec<_>>();
+            report_error(
+                1,
+                "invalid test directives",
+                vec![("errors", errors.into())],
+            )
+        }
+        Error::Directives(errs) => {
+            let errors = errs
+                .iter()
+                .flat_map(|err| {
+                    let message = format!("line {}: {}", err.line, err.message);
+                    report_error(1, &message, vec![]).errors
+                })
+                .collect();
+            Failure { code: 1, errors }
+        }
+        Error::NoRules | Error::InvalidCompiled(_) => report_error(1, &err.to_string(), vec![]),
+        Error::UnknownRule { available, .. } => report_error(
+            1,
+            &err.to_string(),
+            vec![("available", available.clone().into())],
+        ),
+        Error::Normalization(details) => {
+            let failure = if json() {
+                report_error(
+                    -1,
+                    &err.to_string(),
+                    vec![("details", details.as_str().into())],
+                )
+            } else {
+                println!("{}", styles::ERROR.apply_to(err));
+                Failure {
+                    code: -1,
+                    errors: vec![err.to_string()],
+                }
+            };
+            debugln!("{:?}", details);
+            failure
+        }
+    }
+}
+
+fn read_file(file: &Path) -> Outcome<String> {
+    std::fs::read_to_string(file).map_err(|error| {
+        let err = Error::Io {
+            path: file.to_path_buf(),
+            error,
+        };
+        report(&err, file, "")
+    })
+}
+
+fn parse_file(file: &Path, start: Option<&str>, stats: bool) -> Outcome<()> {
+    let size = parse(file, start)?.size();
+    if json() {
+        let mut fields = vec![
+            ("file", file.as_os_str().to_string_lossy().as_ref().into()),
+            ("errors", Json::Array(vec![])),
+        ];
+        if stats {
+            let size = Json::object(vec![
+                ("rules", size.rules.into()),
+                ("terms", size.terms.into()),
+                ("products", size.products.into()),
+            ]);
+            fields.push(("size", size));
+        }
+        println!("{}", Json::object(fields));
+    } else {
+        println!("{}", styles::GOOD.apply_to("No syntax errors were found."));
+        if stats {
+            println!("The normal form has {}.", size);
+        }
+    }
+    Ok(())
+}
+
+fn check_file(
+    rules: &Path,
+    (words, expect): (&Path, bool),
+    start: Option<&str>,
+    verdicts: &mut Verdicts,
+) -> Outcome<()> {
+    let grammar = parse(rules, start)?;
+    let annotated = expect || testfile::is_annotated(words);
+    let path = words;
+    let words = read_file(path)?;
+    if annotated {
+        let expectations = testfile::parse(&words)
+            .map_err(|errs| report(&Error::Directives(errs), path, &words))?;
+        if expectations.is_empty() {
+            let path = path.as_os_str().to_string_lossy();
+            return Err(report_error(
+                1,
+                "the test file doesn't contain any expectations",
+                vec![("file", path.as_ref().into())],
+            ));
+        }
+        return check_expectations(&grammar, &expectations, verdicts);
+    }
+    let words = words.split('\n').collect::<Vec<_>>();
+    let accepts = cyk::Cyk::new(&grammar).accepts_all(&words);
+
+    if json() {
+        let verdicts = words
+            .iter()
+            .zip(accepts.iter())
+            .map(|(w, a)| Json::object(vec![("word", (*w).into()), ("accepted", (*a).into())]))
+            .collect::<Vec<_>>();
+        println!(
+            "{}",
+            Json::object(vec![
+                ("words", verdicts.into()),
+                ("accepted", accepts.iter().filter(|a| **a).count().into()),
+                ("total", words.len().into()),
+            ])
+        );
+        return Ok(());
+    }
+
+    let mut next = HashMap::new();
+    let mut accepted = 0;
+    for (word, &accepts) in words.iter().zip(accepts.iter()) {
+        let changed = verdicts.update(word, accepts, &mut next);
+        let (yn, style) = [("n", &*styles::ERROR), ("y", &*styles::GOOD)][accepts as usize];
+        accepted += accepts as usize;
+        println!(
+            "{} '{}'{}",
+            style.apply_to(format!("[{}]", yn)),
+            word,
+            changed_marker(changed)
+        );
+    }
+    verdicts.finish(next);
+    println!();
+    println!(
+        "{}",
+        styles::INFO.apply_to(format!(
+            "{} out of {} words are recognized by this grammar.",
+            accepted,
+            words.len()
+        ))
+    );
+    Ok(())
+}
+
+/// checks the words of an annotated test file and reports the ones with an unexpected verdict
+fn check_expectations(
+    grammar: &chomsky::Grammar,
+    expectations: &[testfile::Expectation],
+    verdicts: &mut Verdicts,
+) -> Outcome<()> {
+    report_expectations(expectations, |_| grammar, verdicts)
+}
+
+/// checks each expectation with the grammar returned by `grammar_of` and reports mismatches
+fn report_expectations<'a>(
+    expectations: &[testfile::Expectation],
+    grammar_of: impl Fn(&testfile::Expectation) -> &'a chomsky::Grammar,
+    verdicts: &mut Verdicts,
+) -> Outcome<()> {
+    // the words of each grammar are checked in one batch
+    let mut batches = Vec::<(&chomsky::Grammar, Vec<usize>)>::new();
+    for (i, exp) in expectations.iter().enumerate() {
+        let grammar = grammar_of(exp);
+        match batches.iter_mut().find(|(g, _)| std::ptr::eq(*g, grammar)) {
+            Some((_, batch)) => batch.push(i),
+            None => batches.push((grammar, vec![i])),
         }
+    }
+    let mut verdicts_of = vec![false; expectations.len()];
+    for (grammar, batch) in batches {
+        let words = batch
+            .iter()
+            .map(|&i| expectations[i].word.as_str())
+            .collect::<Vec<_>>();
+        let accepts = cyk::Cyk::new(grammar).accepts_all(&words);
+        for (i, accepts) in batch.into_iter().zip(accepts) {
+            verdicts_of[i] = accepts;
+        }
+    }
+
+    let verdict = |accept| if accept { "accepted" } else { "rejected" };
+
+    let mut next = HashMap::new();
+    let mut failed = 0usize;
+    let mut mismatches = Vec::new();
+    for (exp, &accepts) in expectations.iter().zip(verdicts_of.iter()) {
+        let key = exp.key();
+        let changed = verdicts.update(&key, accepts, &mut next);
+        if accepts != exp.accept && json() {
+            failed += 1;
+            mismatches.push(Json::object(vec![
+                ("line", exp.line.into()),
+                ("rule", exp.rule.as_deref().into()),
+                ("word", exp.word.as_str().into()),
+                ("expected", exp.accept.into()),
+                ("accepted", accepts.into()),
+            ]));
+        } else if accepts != exp.accept {
+            failed += 1;
+            let by_rule = match &exp.rule {
+                Some(rule) => format!(" by <{}>", rule),
+                None => String::new(),
+            };
+            println!(
+                "{}: line {}: `{}` should be {}{}, but is {}{}",
+                styles::ERROR.apply_to("mismatch"),
+                exp.line,
+                exp.word,
+                verdict(exp.accept),
+                by_rule,
+                verdict(accepts),
+                changed_marker(changed)
+            );
+        } else if changed && verdicts.seen(&key) && !json() {
+            // new expectations that hold weren't broken before
+            println!(
+                "{}: line {}: `{}` is {} now{}",
+                styles::GOOD.apply_to("fixed"),
+                exp.line,
+                exp.word,
+                verdict(accepts),
+                changed_marker(changed)
+            );
+        }
+    }
+    verdicts.finish(next);
+
+    if json() {
+        println!(
+            "{}",
+            Json::object(vec![
+                ("mismatches", mismatches.into()),
+                ("failed", failed.into()),
+                ("total", expectations.len().into()),
+            ])
+        );
+    }
+
+    if failed == 0 && json() {
+        Ok(())
+    } else if json() {
+        Err(Failure::new(1))
+    } else if failed == 0 {
+        println!(
+            "{}",
+            styles::GOOD.apply_to(format!(
+                "All {} words have the expected verdict.",
+                expectations.len()
+            ))
+        );
+        Ok(())
+    } else {
+        println!();
+        println!(
+            "{}",
+            styles::ERROR.apply_to(format!(
+                "{} out of {} words have an unexpected verdict.",
+                failed,
+                expectations.len()
+            ))
+        );
+        Err(Failure::new(1))
+    }
+}
+
+fn test(file: &Path, verdicts: &mut Verdicts) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let expectations = directives(file, &source).map_err(|err| report(&err, file, &source))?;
+
+    if expectations.is_empty() && !json() {
+        println!(
+            "{}",
+            styles::WARNING
+                .apply_to("There are no `@accept` or `@reject` directives in this file.")
+        );
+        return Ok(());
+    }
+
+    // one grammar for every rule that is tested
+    let top = bnf::root(&rules, None).map_err(|err| report(&err, file, &source))?;
+    let mut grammars = HashMap::new();
+    for exp in expectations.iter() {
+        let rule = exp.rule.as_deref().unwrap_or(top);
+        if grammars.contains_key(rule) {
+            continue;
+        }
+        if !rules.iter().any(|r| r.name == rule) {
+            return Err(report_error(
+                1,
+                &format!("line {}: there is no rule named <{}>", exp.line, rule),
+                vec![("line", exp.line.into())],
+            ));
+        }
+        let grammar = chomsky::Grammar::from_rules(&rules, Some(rule))
+            .map_err(|err| report(&err, file, &source))?;
+        grammars.insert(rule, grammar);
+    }
+
+    report_expectations(
+        &expectations,
+        |exp| &grammars[exp.rule.as_deref().unwrap_or(top)],
+        verdicts,
+    )
+}
+
+fn compare(
+    (file, start): (&Path, Option<&str>),
+    (other_file, other_start): (&Path, Option<&str>),
+    limit: usize,
+    verdicts: &mut Verdicts,
+) -> Outcome<()> {
+    let grammar = parse(file, start)?;
+    let other_grammar = parse(other_file, other_start)?;
+    let compare = compare::Comparison::from_grammars(grammar, other_grammar, limit);
+
+    if json() {
+        let words = |words: &[chomsky::Terminal]| {
+            words
+                .iter()
+                .map(|w| w.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .into()
+        };
+        println!(
+            "{}",
+            Json::object(vec![
+                ("first", words(&compare.first)),
+                ("second", words(&compare.second)),
+                ("both", words(&compare.both)),
+            ])
+        );
+        return Ok(());
+    }
+
+    let mut next = HashMap::new();
+    let mut mapped = |words: &[chomsky::Terminal], side: &str| {
+        words
+            .iter()
+            .map(|cs| {
+                let word = cs.iter().cloned().collect::<String>();
+                let changed = verdicts.update(&format!("{}:{}", side, word), true, &mut next);
+                let word = format!("{:?}", word);
+                if changed {
+                    styles::WARNING.apply_to(word).to_string()
+                } else {
+                    word
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
     };
-    debugln!("{:?}\n", rules);
-    let mut grammar = bnf::to_grammar(&rules, &rules[0].name);
-    grammar.simplify();
-    grammar.normalize();
-    debugln!("{}", grammar);
-    match chomsky::Grammar::from_normalized(&grammar) {
-        Ok(grammar) => grammar,
-        Err(err) => {
+    println!(
+        "words only accepted by the first grammar:\n[{}]",
+        mapped(&compare.first, "first")
+    );
+    println!(
+        "words only accepted by the second grammar:\n[{}]",
+        mapped(&compare.second, "second")
+    );
+    for side in ["first", "second"].iter() {
+        let prefix = format!("{}:", side);
+        let removed = verdicts
+            .removed(&next)
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .map(|word| format!("{:?}", word))
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
             println!(
                 "{}",
-                styles::ERROR.apply_to("internal error: failed to normalize grammar")
+                styles::WARNING.apply_to(format!(
+                    "no longer only accepted by the {} grammar:\n[{}]",
+                    side,
+                    removed.join(", ")
+                ))
             );
-            debugln!("{:?}", err);
-            std::process::exit(-1);
         }
     }
+    verdicts.finish(next);
+    Ok(())
 }
 
-fn read_file(file: &Path) -> String {
-    fn inner(file: &Path) -> std::io::Result<String> {
-        let mut file = std::fs::File::open(file)?;
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)?;
-        Ok(buf)
-    }
-    match inner(file) {
-        Ok(content) => content,
+/// loads a grammar without reporting errors, as they are part of the grading report instead
+fn load_submission(file: &Path, start: Option<&str>) -> Result<chomsky::Grammar, grade::Status> {
+    let options = chomsky::Options {
+        start: start.map(String::from),
+    };
+    chomsky::Grammar::from_file(file, &options).map_err(|err| grade::Status::from(&err))
+}
+
+fn grade(
+    reference: &Path,
+    submissions: &Path,
+    (limit, max_length): (usize, Option<usize>),
+    summary: Option<&Path>,
+    start: Option<&str>,
+) -> Outcome<()> {
+    let reference = parse(reference, start)?;
+
+    let mut files = match std::fs::read_dir(submissions) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter(|p| {
+                !p.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .starts_with('.')
+            })
+            .collect::<Vec<_>>(),
         Err(_) => {
+            let path = submissions.as_os_str().to_string_lossy();
+            return Err(report_error(
+                2,
+                &format!("failed to read directory {}", path),
+                vec![("directory", path.as_ref().into())],
+            ));
+        }
+    };
+    files.sort();
+
+    let producer = |grammar| {
+        let producer = producer::Producer::new(grammar);
+        match max_length {
+            Some(max) => producer.search_space(max),
+            None => producer,
+        }
+    };
+
+    let mut reports = Vec::new();
+    for file in files.iter() {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let report = match load_submission(file, start) {
+            Ok(grammar) => {
+                let comparison = compare::Comparison::from_producers(
+                    producer(reference.clone()),
+                    producer(grammar),
+                    limit,
+                );
+                grade::Report::from_comparison(&name, &comparison, max_length)
+            }
+            Err(status) => grade::Report::failed(&name, status),
+        };
+        if !json() {
+            print_report(&report);
+        }
+        reports.push(report);
+    }
+
+    if json() {
+        let reports = reports
+            .iter()
+            .map(grade::Report::to_json)
+            .collect::<Vec<_>>();
+        println!("{}", Json::object(vec![("submissions", reports.into())]));
+    } else {
+        let average =
+            reports.iter().map(grade::Report::score).sum::<f64>() / reports.len().max(1) as f64;
+        println!();
+        println!(
+            "{}",
+            styles::INFO.apply_to(format!(
+                "Graded {} submissions with an average score of {:.1}%.",
+                reports.len(),
+                100.0 * average
+            ))
+        );
+    }
+
+    if let Some(summary) = summary {
+        let content = if summary.extension().is_some_and(|e| e == "json") {
+            let reports = reports
+                .iter()
+                .map(grade::Report::to_json)
+                .collect::<Vec<_>>();
+            format!("{}\n", Json::from(reports))
+        } else {
+            let mut csv = format!("{}\n", grade::Report::csv_header());
+            for report in reports.iter() {
+                csv.push_str(&report.to_csv());
+                csv.push('\n');
+            }
+            csv
+        };
+        if std::fs::write(summary, content).is_err() {
+            let path = summary.as_os_str().to_string_lossy();
+            return Err(report_error(
+                2,
+                &format!("failed to write file {}", path),
+                vec![("file", path.as_ref().into())],
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// prints the grading report of a single submission
+fn print_report(report: &grade::Report) {
+    /// maximum number of example words printed per submission
+    const EXAMPLES: usize = 5;
+
+    let name = styles::INFO.apply_to(&report.name);
+    match report.status {
+        grade::Status::Ok => {}
+        grade::Status::SyntaxErrors(n) => {
             println!(
-                "{}: failed to read file {}",
-                styles::ERROR.apply_to("error"),
-                file.as_os_str().to_string_lossy()
+                "{}: {}",
+                name,
+                styles::ERROR.apply_to(format!("{} syntax errors", n))
             );
-            std::process::exit(2)
+            return;
         }
+        ref status => {
+            println!("{}: {}", name, styles::ERROR.apply_to(status.name()));
+            return;
+        }
+    }
+
+    let score = format!("{:.1}%", 100.0 * report.score());
+    let style = if report.false_accepts.is_empty() && report.false_rejects.is_empty() {
+        &*styles::GOOD
+    } else {
+        &*styles::WARNING
+    };
+    println!(
+        "{}: {} ({} false accepts, {} false rejects)",
+        name,
+        style.apply_to(score),
+        report.false_accepts.len(),
+        report.false_rejects.len()
+    );
+    let examples = |words: &[chomsky::Terminal]| {
+        words
+            .iter()
+            .take(EXAMPLES)
+            .map(|w| w.iter().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    if !report.false_accepts.is_empty() {
+        println!("    false accepts: {:?}", examples(&report.false_accepts));
+    }
+    if !report.false_rejects.is_empty() {
+        println!("    false rejects: {:?}", examples(&report.false_rejects));
     }
 }
 
-fn main() {
-    let args = Options::from_args();
+#[cfg(feature = "serde")]
+fn compile(file: &Path, output: Option<&Path>, start: Option<&str>) -> Outcome<()> {
+    let source = read_file(file)?;
+    let compiled = compiled::Compiled::from_source(&source, start)
+        .map_err(|err| report(&err, file, &source))?;
+    write_output(output, &(compiled.to_json() + "\n"))?;
 
-    log::enable(args.debug);
+    let output = match output {
+        Some(output) => output,
+        None => return Ok(()),
+    };
+    if json() {
+        let path = output.as_os_str().to_string_lossy();
+        println!("{}", Json::object(vec![("output", path.as_ref().into())]));
+    } else {
+        println!(
+            "{}",
+            styles::GOOD.apply_to(format!(
+                "Compiled grammar of <{}> was written to {}.",
+                compiled.start,
+                output.as_os_str().to_string_lossy()
+            ))
+        );
+    }
+    Ok(())
+}
 
-    use Command::*;
-    match args.cmd {
-        Parse { file } => {
-            parse(&file);
-            println!("{}", styles::GOOD.apply_to("No syntax errors were found."))
+fn codegen(file: &Path, lang: &str, output: Option<&Path>, start: Option<&str>) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let root = bnf::root(&rules, start).map_err(|err| report(&err, file, &source))?;
+    let path = file.as_os_str().to_string_lossy();
+    let code = match lang {
+        "rust" => codegen::rust(&rules, root, &path),
+        other => {
+            return Err(report_error(
+                1,
+                &format!("unsupported language `{}`", other),
+                vec![],
+            ));
         }
-        Check { file, word } => {
-            let grammar = parse(&file);
-            let verdict = if grammar.accepts(&word) {
-                styles::GOOD.apply_to("accepted")
-            } else {
-                styles::ERROR.apply_to("rejected")
-            };
-            println!("`{}` is {} by this grammar.", word, verdict);
+    };
+    let code = code.map_err(|err| report(&err, file, &source))?;
+    write_output(output, &code)
+}
+
+fn ll1(file: &Path, start: Option<&str>) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let root = bnf::root(&rules, start).map_err(|err| report(&err, file, &source))?;
+    let analysis = ll1::Analysis::new(&rules, root);
+    let conflicts = analysis.conflicts();
+    let left_recursion = analysis.left_recursion();
+    let left_recursive = left_recursion
+        .iter()
+        .map(|c| analysis.names[c[0]].clone())
+        .collect::<Vec<_>>();
+    let first = |r: usize| {
+        analysis.first[r]
+            .iter()
+            .map(|c| Some(*c))
+            .collect::<Vec<_>>()
+    };
+
+    if json() {
+        let symbols = |symbols: &[ll1::Lookahead]| {
+            symbols
+                .iter()
+                .map(|s| s.map(String::from))
+                .collect::<Vec<_>>()
+                .into()
+        };
+        let sets = (0..analysis.names.len())
+            .map(|r| {
+                let follow = analysis.follow[r].iter().cloned().collect::<Vec<_>>();
+                Json::object(vec![
+                    ("rule", analysis.names[r].as_str().into()),
+                    ("nullable", analysis.nullable[r].into()),
+                    ("first", symbols(&first(r))),
+                    ("follow", symbols(&follow)),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let json_conflicts = conflicts
+            .iter()
+            .map(|c| {
+                let alternatives = c
+                    .alternatives()
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>();
+                let lookahead = c.symbols().into_iter().collect::<Vec<_>>();
+                Json::object(vec![
+                    ("kind", c.kind().into()),
+                    ("rule", c.rule().into()),
+                    ("alternatives", alternatives.into()),
+                    ("symbols", symbols(&lookahead)),
+                ])
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "{}",
+            Json::object(vec![
+                ("rules", sets.into()),
+                ("conflicts", json_conflicts.into()),
+                ("left_recursive", left_recursive.clone().into()),
+                ("ll1", analysis.is_ll1().into()),
+            ])
+        );
+    } else {
+        let width = analysis
+            .names
+            .iter()
+            .map(|n| n.len() + 2)
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        println!(
+            "{}",
+            styles::INFO.apply_to(format!(
+                "{:<width$}  nullable  FIRST / FOLLOW ($ is the end of the input)",
+                "rule",
+                width = width
+            ))
+        );
+        for (r, name) in analysis.names.iter().enumerate() {
+            println!(
+                "{:<width$}  {:<8}  {{{}}} / {{{}}}",
+                format!("<{}>", name),
+                if analysis.nullable[r] { "yes" } else { "no" },
+                ll1::format_symbols(&first(r)),
+                ll1::format_symbols(&analysis.follow[r]),
+                width = width
+            );
         }
-        CheckFile { rules, words } => {
-            let grammar = parse(&rules);
-            let words = read_file(&words);
-            let words = words.split('\n').collect::<Vec<_>>();
-
-            let mut accepted = 0;
-            for word in words.iter() {
-                let accepts = grammar.accepts(word) as usize;
-                let (yn, style) = [("n", &*styles::ERROR), ("y", &*styles::GOOD)][accepts];
-                accepted += accepts;
-                println!("{} '{}'", style.apply_to(format!("[{}]", yn)), word);
-            }
-            println!();
+        println!();
+        for conflict in conflicts.iter() {
+            let kind = format!("{} conflict", conflict.kind());
+            println!("{} in {}", styles::ERROR.apply_to(kind), conflict);
+        }
+        for cycle in left_recursion.iter() {
+            println!(
+                "{} in <{}>: {}",
+                styles::ERROR.apply_to("left recursion"),
+                analysis.names[cycle[0]],
+                analysis.format_cycle(cycle)
+            );
+        }
+        if analysis.is_ll1() {
+            println!("{}", styles::GOOD.apply_to("The grammar is LL(1)."));
+        } else {
             println!(
                 "{}",
-                styles::INFO.apply_to(format!(
-                    "{} out of {} words are recognized by this grammar.",
-                    accepted,
-                    words.len()
+                styles::ERROR.apply_to(format!(
+                    "The grammar isn't LL(1), it has {} and {}.",
+                    counted(conflicts.len(), "conflict"),
+                    counted(left_recursive.len(), "left recursive rule")
                 ))
             );
         }
-        Compare {
-            file,
-            other_file,
-            limit,
-        } => {
-            let grammar = parse(&file);
-            let other_grammar = parse(&other_file);
-            let compare = compare::Comparison::from_grammars(grammar, other_grammar, limit);
-            let mapped = |words: &[chomsky::Terminal]| {
-                words
+    }
+
+    if analysis.is_ll1() {
+        Ok(())
+    } else {
+        Err(Failure::new(1))
+    }
+}
+
+fn forest(
+    (file, start): (&Path, Option<&str>),
+    word: &str,
+    (dot, output): (bool, Option<&Path>),
+    limit: usize,
+) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let root = bnf::root(&rules, start).map_err(|err| report(&err, file, &source))?;
+    let forest = forest::Forest::new(&rules, root, word);
+    if dot {
+        return write_output(output, &forest.to_dot());
+    }
+
+    let trees = forest.trees().take(limit).collect::<Vec<_>>();
+    if json() {
+        fn tree_json(tree: &forest::Tree) -> Json {
+            match tree {
+                forest::Tree::Text(text) => text.as_str().into(),
+                forest::Tree::Rule { name, children } => Json::object(vec![
+                    ("rule", name.as_str().into()),
+                    (
+                        "children",
+                        children.iter().map(tree_json).collect::<Vec<_>>().into(),
+                    ),
+                ]),
+            }
+        }
+        println!(
+            "{}",
+            Json::object(vec![
+                ("word", word.into()),
+                ("accepted", forest.accepts().into()),
+                (
+                    "count",
+                    match forest.count() {
+                        forest::Count::Finite(n) => n.into(),
+                        _ => Json::Null,
+                    },
+                ),
+                (
+                    "saturated",
+                    (forest.count() == forest::Count::Saturated).into(),
+                ),
+                ("nodes", forest.nodes().len().into()),
+                (
+                    "trees",
+                    trees.iter().map(tree_json).collect::<Vec<_>>().into()
+                ),
+            ])
+        );
+        return Ok(());
+    }
+
+    if !forest.accepts() {
+        println!(
+            "`{}` is {} by this grammar.",
+            word,
+            styles::ERROR.apply_to("rejected")
+        );
+        return Ok(());
+    }
+    let count = match forest.count() {
+        forest::Count::Finite(1) => "1 parse tree".to_string(),
+        forest::Count::Finite(n) => format!("{} parse trees", n),
+        forest::Count::Saturated => "more than 2^128 parse trees".to_string(),
+        forest::Count::Infinite => "infinitely many parse trees".to_string(),
+    };
+    let style = if forest.count() == forest::Count::Finite(1) {
+        &*styles::GOOD
+    } else {
+        &*styles::WARNING
+    };
+    println!(
+        "`{}` has {}, in a forest of {} nodes.",
+        word,
+        style.apply_to(count),
+        forest.nodes().len()
+    );
+    for (i, tree) in trees.iter().enumerate() {
+        print!("\ntree {}:\n{}", i + 1, tree);
+    }
+    let more = match forest.count() {
+        forest::Count::Finite(n) => n > trees.len() as u128,
+        _ => true,
+    };
+    if more {
+        println!("...");
+    }
+    Ok(())
+}
+
+fn lr(file: &Path, kind: lr::Kind, start: Option<&str>) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let root = bnf::root(&rules, start).map_err(|err| report(&err, file, &source))?;
+    let automaton =
+        lr::Automaton::from_rules(&rules, root, kind).map_err(|err| report(&err, file, &source))?;
+    let conflicts = automaton.conflicts();
+    let symbols = |symbols: &std::collections::BTreeSet<ll1::Lookahead>| {
+        symbols
+            .iter()
+            .map(|s| s.map(String::from))
+            .collect::<Vec<_>>()
+            .into()
+    };
+
+    if json() {
+        let states = automaton
+            .states
+            .iter()
+            .enumerate()
+            .map(|(s, state)| {
+                let items = state
+                    .items
                     .iter()
-                    .map(|cs| cs.iter().cloned().collect::<String>())
-                    .collect::<Vec<_>>()
-            };
+                    .map(|&item| automaton.format_item(item))
+                    .collect::<Vec<_>>();
+                let transitions = state
+                    .transitions
+                    .iter()
+                    .map(|(symbol, &target)| {
+                        Json::object(vec![
+                            ("symbol", automaton.format_symbol(symbol).into()),
+                            ("target", target.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                let reductions = state
+                    .reductions
+                    .iter()
+                    .map(|(production, lookahead)| {
+                        Json::object(vec![
+                            (
+                                "production",
+                                automaton.format_production(*production).into(),
+                            ),
+                            ("symbols", symbols(lookahead)),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Json::object(vec![
+                    ("state", s.into()),
+                    ("items", items.into()),
+                    ("transitions", transitions.into()),
+                    ("reductions", reductions.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let json_conflicts = conflicts
+            .iter()
+            .map(|c| {
+                let productions = c
+                    .productions
+                    .iter()
+                    .map(|&p| automaton.format_production(p))
+                    .collect::<Vec<_>>();
+                Json::object(vec![
+                    ("state", c.state.into()),
+                    ("kind", c.kind().into()),
+                    ("symbols", symbols(&c.symbols)),
+                    ("productions", productions.into()),
+                    ("prefix", c.prefix.as_str().into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "{}",
+            Json::object(vec![
+                ("kind", kind.name().into()),
+                ("states", states.into()),
+                ("conflicts", json_conflicts.into()),
+            ])
+        );
+    } else {
+        println!("{}", automaton);
+        for conflict in conflicts.iter() {
+            let kind = format!("{} conflict", conflict.kind());
             println!(
-                "words only accepted by the first grammar:\n{:?}",
-                mapped(&compare.first)
+                "{} in {}",
+                styles::ERROR.apply_to(kind),
+                automaton.format_conflict(conflict)
             );
+        }
+        if conflicts.is_empty() {
             println!(
-                "words only accepted by the second grammar:\n{:?}",
-                mapped(&compare.second)
+                "{}",
+                styles::GOOD.apply_to(format!("The grammar is {}.", kind.name()))
+            );
+        } else {
+            println!(
+                "{}",
+                styles::ERROR.apply_to(format!(
+                    "The grammar isn't {}, it has {}.",
+                    kind.name(),
+                    counted(conflicts.len(), "conflict")
+                ))
             );
         }
-        ProduceWords { file, limit } => {
-            let grammar = parse(&file);
-            let words = producer::Producer::new(grammar)
-                .map(|w| w.into_iter().collect::<String>())
-                .take(limit)
+    }
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(Failure::new(1))
+    }
+}
+
+fn simplify(file: &Path, output: Option<&Path>) -> Outcome<()> {
+    let (source, mut rules) = parse_rules(file)?;
+    for rule in rules.iter_mut() {
+        rule.def.simplify();
+    }
+    write_rules(&rules, &source, output)
+}
+
+fn left_recursion(file: &Path, fix: bool, output: Option<&Path>) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let root = bnf::root(&rules, None).map_err(|err| report(&err, file, &source))?;
+
+    if fix {
+        let fixed = match transform::eliminate_left_recursion(&rules) {
+            Ok(fixed) => fixed,
+            Err(message) => {
+                return Err(report_error(1, &message, vec![]));
+            }
+        };
+        return write_rules(&fixed, &source, output);
+    }
+
+    let analysis = ll1::Analysis::new(&rules, root);
+    let cycles = analysis.left_recursion();
+    if json() {
+        let cycles = cycles
+            .iter()
+            .map(|c| {
+                c.iter()
+                    .map(|&r| analysis.names[r].clone())
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .collect::<Vec<Json>>();
+        println!("{}", Json::object(vec![("left_recursion", cycles.into())]));
+    } else if cycles.is_empty() {
+        println!("{}", styles::GOOD.apply_to("There is no left recursion."));
+    } else {
+        for cycle in cycles.iter() {
+            println!(
+                "{} in <{}>: {}",
+                styles::ERROR.apply_to("left recursion"),
+                analysis.names[cycle[0]],
+                analysis.format_cycle(cycle)
+            );
+        }
+        println!();
+        println!(
+            "{}",
+            styles::INFO.apply_to("Run with --fix to get equivalent rules without left recursion.")
+        );
+    }
+    if cycles.is_empty() {
+        Ok(())
+    } else {
+        Err(Failure::new(1))
+    }
+}
+
+fn left_factor(file: &Path, output: Option<&Path>, verify: Option<usize>) -> Outcome<()> {
+    let (source, rules) = parse_rules(file)?;
+    let factored = transform::left_factor(&rules);
+    let differences = match verify {
+        Some(limit) => Some(
+            transform::differences(&rules, &factored, limit)
+                .map_err(|err| report(&err, file, &source))?,
+        ),
+        None => None,
+    };
+
+    let words = |words: &[chomsky::Terminal]| {
+        words
+            .iter()
+            .map(|w| w.iter().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    if json() {
+        let mut fields = Vec::new();
+        match output {
+            Some(_) => write_rules(&factored, &source, output)?,
+            None => fields.push(("rules", rules_source(&factored, &source).into())),
+        }
+        if let Some(differences) = differences.as_ref() {
+            let differences = differences
+                .iter()
+                .map(|(rule, c)| {
+                    Json::object(vec![
+                        ("rule", rule.as_str().into()),
+                        ("only_before", words(&c.first).into()),
+                        ("only_after", words(&c.second).into()),
+                    ])
+                })
                 .collect::<Vec<_>>();
-            println!("\nwords accepted by this grammar:");
-            println!("{:?}", words);
+            fields.push(("differences", differences.into()));
+        }
+        println!("{}", Json::object(fields));
+    } else {
+        write_rules(&factored, &source, output)?;
+        // the rules may be printed, so the verification goes to stderr
+        match differences.as_ref() {
+            Some(differences) if differences.is_empty() => eprintln!(
+                "{}",
+                styles::GOOD.apply_to(format!(
+                    "Every rule accepts the same words as before, among the first {}.",
+                    verify.unwrap_or_default()
+                ))
+            ),
+            Some(differences) => {
+                for (rule, c) in differences.iter() {
+                    eprintln!(
+                        "{}: <{}> accepts different words, only before: {:?}, only after: {:?}",
+                        styles::ERROR.apply_to("error"),
+                        rule,
+                        words(&c.first),
+                        words(&c.second)
+                    );
+                }
+            }
+            None => {}
+        }
+    }
+
+    match differences {
+        Some(differences) if !differences.is_empty() => Err(Failure::new(1)),
+        _ => Ok(()),
+    }
+}
+
+/// transformed rules in ebnf syntax, keeping the test directives of the original source
+fn rules_source(rules: &[bnf::BnfRule], source: &str) -> String {
+    let mut content = String::new();
+    for rule in rules.iter() {
+        content.push_str(&rule.to_string());
+        content.push('\n');
+    }
+    for line in source.lines().filter(|l| testfile::is_directive(l)) {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content
+}
+
+/// writes transformed rules in ebnf syntax, keeping the test directives of the original source
+fn write_rules(rules: &[bnf::BnfRule], source: &str, output: Option<&Path>) -> Outcome<()> {
+    write_output(output, &rules_source(rules, source))
+}
+
+/// writes `content` to `output`, or prints it if there is no output file
+fn write_output(output: Option<&Path>, content: &str) -> Outcome<()> {
+    let output = match output {
+        Some(output) => output,
+        None => {
+            print!("{}", content);
+            return Ok(());
+        }
+    };
+    if std::fs::write(output, content).is_err() {
+        let path = output.as_os_str().to_string_lossy();
+        return Err(report_error(
+            2,
+            &format!("failed to write file {}", path),
+            vec![("file", path.as_ref().into())],
+        ));
+    }
+    Ok(())
+}
+
+/// `n` followed by `noun`, with an `s` appended unless there is exactly one
+fn counted(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+/// runs the command once, or whenever one of the files changes if `watch` is set
+fn run(
+    watch: bool,
+    files: &[&Path],
+    mut command: impl FnMut(&mut Verdicts) -> Outcome<()>,
+) -> Outcome<()> {
+    let mut verdicts = Verdicts::new();
+    if !watch {
+        return command(&mut verdicts);
+    }
+
+    let term = console::Term::stdout();
+    let mut watcher = watch::Watcher::new(files);
+    loop {
+        if json() {
+            // every run prints a json object on its own line
+            command(&mut verdicts).ok();
+            watcher.wait();
+            continue;
+        }
+        term.clear_screen().ok();
+        let errors = match command(&mut verdicts) {
+            Ok(()) => Vec::new(),
+            Err(failure) => failure.errors,
+        };
+        if let Some(change) = verdicts.errors(errors) {
+            println!("{}", error_change_line(change));
         }
+        println!();
+        println!(
+            "{}",
+            styles::INFO.apply_to("Watching for changes, press Ctrl-C to quit.")
+        );
+        watcher.wait();
+    }
+}
+
+fn main() {
+    let args = Options::from_args();
+
+    log::enable(args.debug);
+    JSON.store(args.format == Format::Json, Ordering::SeqCst);
+
+    use Command::*;
+    let result = match args.cmd {
+        Parse {
+            file,
+            start,
+            watch,
+            stats,
+        } => run(watch, &[&file], |_| {
+            parse_file(&file, start.start.as_deref(), stats)
+        }),
+        Check { file, word, start } => parse(&file, start.start.as_deref()).map(|grammar| {
+            if json() {
+                let accepted = grammar.accepts(&word);
+                let fields = vec![("word", word.into()), ("accepted", accepted.into())];
+                println!("{}", Json::object(fields));
+                return;
+            }
+            let verdict = if grammar.accepts(&word) {
+                styles::GOOD.apply_to("accepted")
+            } else {
+                styles::ERROR.apply_to("rejected")
+            };
+            println!("`{}` is {} by this grammar.", word, verdict);
+        }),
+        CheckFile {
+            rules,
+            words,
+            start,
+            expect,
+            watch,
+        } => run(watch, &[&rules, &words], |v| {
+            check_file(&rules, (&words, expect), start.start.as_deref(), v)
+        }),
+        Test { file, watch } => run(watch, &[&file], |v| test(&file, v)),
+        Compare {
+            file,
+            other_file,
+            limit,
+            start1,
+            start2,
+            watch,
+        } => run(watch, &[&file, &other_file], |v| {
+            compare(
+                (&file, start1.as_deref()),
+                (&other_file, start2.as_deref()),
+                limit,
+                v,
+            )
+        }),
+        Grade {
+            reference,
+            submissions,
+            limit,
+            max_length,
+            summary,
+            start,
+        } => grade(
+            &reference,
+            &submissions,
+            (limit, max_length),
+            summary.as_deref(),
+            start.start.as_deref(),
+        ),
+        #[cfg(feature = "serde")]
+        Compile {
+            file,
+            output,
+            start,
+        } => compile(&file, output.as_deref(), start.start.as_deref()),
+        Codegen {
+            file,
+            lang,
+            output,
+            start,
+        } => codegen(&file, &lang, output.as_deref(), start.start.as_deref()),
+        Ll1 { file, start } => ll1(&file, start.start.as_deref()),
+        Simplify { file, output } => simplify(&file, output.as_deref()),
+        LeftRecursion { file, fix, output } => left_recursion(&file, fix, output.as_deref()),
+        LeftFactor {
+            file,
+            output,
+            verify,
+        } => left_factor(&file, output.as_deref(), verify),
+        Lr { file, kind, start } => lr(&file, kind, start.start.as_deref()),
+        Forest {
+            file,
+            word,
+            dot,
+            trees,
+            output,
+            start,
+        } => forest(
+            (&file, start.start.as_deref()),
+            &word,
+            (dot, output.as_deref()),
+            trees,
+        ),
+        ProduceWords { file, limit, start } => {
+            parse(&file, start.start.as_deref()).map(|grammar| {
+                let words = producer::Producer::new(grammar)
+                    .map(|w| w.into_iter().collect::<String>())
+                    .take(limit)
+                    .collect::<Vec<_>>();
+                if json() {
+                    println!("{}", Json::object(vec![("words", words.into())]));
+                    return;
+                }
+                println!("\nwords accepted by this grammar:");
+                println!("{:?}", words);
+            })
+        }
+    };
+
+    if let Err(Failure { code, .. }) = result {
+        std::process::exit(code);
     }
 }
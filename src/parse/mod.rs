@@ -39,6 +39,36 @@ pub struct ParseError {
     location: Option<Location>,
 }
 impl ParseError {
+    /// tokens that would have been valid instead
+    pub fn expected(&self) -> &[Token] {
+        &self.expected
+    }
+
+    /// the token that was found, or `None` if the line ended early
+    pub fn got(&self) -> Option<&Token> {
+        self.got.as_ref()
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// returns line and column of the error in `source`, both starting at 1
+    pub fn position(&self, source: &str) -> Option<(usize, usize)> {
+        let start = *self.location.as_ref()?.start();
+        let mut line = 1;
+        let mut column = 1;
+        for ch in source.chars().take(start) {
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        Some((line, column))
+    }
+
     pub fn message(&self) -> String {
         let exp = self
             .expected
@@ -82,13 +112,13 @@ pub fn parse(tokens: &[(Location, Token)]) -> Result<Vec<BnfRule>, Vec<ParseErro
     let lines = tokens.split(|t| t.1 == Token::Newline);
     let mut rules = Vec::new();
     let mut errors = Vec::new();
-    let mut line_count = 0;
 
     for mut line in lines {
-        if line.is_empty() {
-            continue;
-        }
-        line_count += 1;
+        // the position right after the line, for errors about lines that end early
+        let end = match line.last() {
+            Some((location, _)) => location.end() + 1,
+            None => continue,
+        };
         let parse_result = (|| {
             let line = &mut line;
 
@@ -106,11 +136,7 @@ pub fn parse(tokens: &[(Location, Token)]) -> Result<Vec<BnfRule>, Vec<ParseErro
         })();
         let parse_result = parse_result.map_err(|mut e: ParseError| {
             if e.location.is_none() {
-                e.location = tokens
-                    .iter()
-                    .filter(|(_, t)| t == &Token::Newline)
-                    .nth(line_count - 1)
-                    .map(|(l, _)| l.clone());
+                e.location = Some(end..=end);
             }
             e
         });
@@ -210,23 +236,29 @@ pub fn format_errors(file: &str, source: &str, errors: Vec<ParseError>) -> Strin
         .map(|e| {
             let location = match &e.location {
                 Some(l) => l,
-                None => return String::from("TODO"),
+                None => return format!("{}: {}\n\n", ERROR.apply_to("error"), e.message()),
             };
-            let (mut l_from, mut l_to) = location.clone().into_inner();
+            // locations may point right after the end of the source
+            let (from, to) = location.clone().into_inner();
+            let from = from.min(chars.len());
+            let to = to.clamp(from, chars.len());
+
+            let mut l_from = from;
             while l_from > 0 && chars[l_from - 1] != '\n' {
                 l_from -= 1;
             }
+            let mut l_to = to;
             while l_to < chars.len() && chars[l_to] != '\n' {
                 l_to += 1;
             }
-            let (mut c_from, mut c_to) = location.clone().into_inner();
-            c_from -= l_from;
-            c_to -= l_from;
+            let (c_from, c_to) = (from - l_from, to - l_from);
 
             let line_chars = chars[l_from..l_to]
                 .iter()
                 .chain(Some(&' '))
-                .collect::<String>();
+                .copied()
+                .collect::<Vec<_>>();
+            let text = |range: &[char]| range.iter().collect::<String>();
 
             let line_number = 1 + chars[..l_from].iter().filter(|c| **c == '\n').count();
             let line_fmt = |l| INFO.apply_to(format!("{: >3} |   ", l));
@@ -239,9 +271,9 @@ pub fn format_errors(file: &str, source: &str, errors: Vec<ParseError>) -> Strin
                 c_from + 1,
                 line_fmt("".into()),
                 line_fmt(line_number.to_string()),
-                &line_chars[..c_from],
-                ERROR.apply_to(&line_chars[c_from..=c_to]),
-                &line_chars[c_to + 1..],
+                text(&line_chars[..c_from]),
+                ERROR.apply_to(text(&line_chars[c_from..=c_to])),
+                text(&line_chars[c_to + 1..]),
                 line_fmt("".into()),
             );
 
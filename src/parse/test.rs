@@ -84,8 +84,8 @@ fn generative() {
         (text, rule)
     };
 
-    let prod: Vec<&dyn Fn(&[(String, BnfPart)]) -> (String, BnfPart)> =
-        vec![&choice, &concat, &opt, &rep];
+    type Combinator<'a> = &'a dyn Fn(&[(String, BnfPart)]) -> (String, BnfPart);
+    let prod: Vec<Combinator> = vec![&choice, &concat, &opt, &rep];
 
     let mut elems = atoms.clone();
     let mut rng = rand::thread_rng();
@@ -115,11 +115,7 @@ fn generative() {
 }
 
 #[cfg(test)]
-fn timeout_test(
-    timeout: f64,
-    timeout_msg: &str,
-    test: impl 'static + Send + Sync + FnOnce() -> (),
-) {
+fn timeout_test(timeout: f64, timeout_msg: &str, test: impl 'static + Send + Sync + FnOnce()) {
     use std::sync::mpsc::*;
     use std::thread::*;
     use std::time::*;
@@ -186,8 +182,7 @@ fn fuzzing() {
             .take(input.len())
             .collect::<std::string::String>();
 
-        let mut input: &[_] = &input;
-        let parsed = parse(&mut input);
+        let parsed = parse(&input);
         match parsed {
             Ok(_) => {
                 _correct += 1;
@@ -199,3 +194,32 @@ fn fuzzing() {
     }
     println!("{}/{} successful parses", _correct, reps);
 }
+
+/// tests for crashes from the source text to checking a word, with random sources
+#[test]
+fn fuzzing_pipeline() {
+    use crate::chomsky::Grammar;
+    use rand::prelude::*;
+
+    let chars = "<>=()[]{}|\\ \n\tabä".chars().collect::<Vec<_>>();
+    let mut rng = rand::thread_rng();
+    for _ in 0..20_000 {
+        let len = rng.gen_range(0..40);
+        let mut source = "<s> <= ".to_string();
+        source.extend((0..len).map(|_| chars[rng.gen_range(0..chars.len())]));
+        let source = &source[rng.gen_range(0..2) * "<s> <= ".len()..];
+        match parse(&crate::lex::lex(source)) {
+            Ok(rules) => {
+                if let Ok(grammar) = Grammar::from_rules(&rules, None) {
+                    grammar.accepts("abä");
+                }
+            }
+            Err(errs) => {
+                for e in errs.iter() {
+                    e.position(source);
+                }
+                format_errors("fuzz.txt", source, errs);
+            }
+        }
+    }
+}
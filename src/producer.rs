@@ -84,8 +84,7 @@ impl Producer {
     pub fn all_buffered_words(&self) -> Vec<Terminal> {
         self.words
             .iter()
-            .map(|w| &w[self.grammar.start])
-            .flatten()
+            .flat_map(|w| &w[self.grammar.start])
             .cloned()
             .collect()
     }
@@ -116,7 +115,8 @@ impl Producer {
     }
     /// whether it's possible that there are more words still
     fn finished(&self) -> bool {
-        self.words.len() > 2 * self.current_longest() + self.longest_literal() + 1 || self.current_longest() > self.search_space.unwrap_or(99999999)
+        self.words.len() > 2 * self.current_longest() + self.longest_literal() + 1
+            || self.current_longest() > self.search_space.unwrap_or(99999999)
     }
     /// reference to the longest currently found words
     fn long_words(&self) -> &[Terminal] {
@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+/// Removes its directory when dropped
+pub(crate) struct Guard(PathBuf);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// the path of `file` in a new temporary directory, which is removed with the returned guard
+///
+/// The directory is named after `test`, so tests that run in parallel need different names.
+pub(crate) fn scratch(test: &str, file: &str) -> (PathBuf, Guard) {
+    let dir = std::env::temp_dir().join(format!("parsley-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    (dir.join(file), Guard(dir))
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+#[cfg(test)]
+mod test;
+
+/// A word together with the verdict a grammar is expected to give
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expectation {
+    /// line number in the test file, starting at 1
+    pub line: usize,
+    /// rule that should be checked, or `None` for the top-level rule
+    pub rule: Option<String>,
+    pub word: String,
+    /// whether the word must be accepted or rejected
+    pub accept: bool,
+}
+
+impl Expectation {
+    /// identifies the expectation by what it checks instead of its line, so it is recognized
+    /// after lines above it are inserted or removed
+    pub fn key(&self) -> String {
+        format!("{:?} {:?} {}", self.rule, self.word, self.accept)
+    }
+}
+
+/// extension of test files in the annotated format
+pub const EXTENSION: &str = "expect";
+
+/// returns whether a test file uses the annotated format, going by its extension
+///
+/// In this format every line is either empty, a comment starting with `#`, or a word prefixed
+/// with `+` (must be accepted) or `-` (must be rejected). Other files are plain lists of
+/// words, even if all of them start with `+` or `-`.
+pub fn is_annotated(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// reads the expectations of an annotated test file
+///
+/// Everything after the `+` or `-` is taken verbatim as the word, so `+` on its own expects the
+/// empty word to be accepted. Lines that are neither empty, comments nor expectations are errors.
+pub fn parse(source: &str) -> Result<Vec<Expectation>, Vec<DirectiveError>> {
+    let mut expectations = Vec::new();
+    let mut errors = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        let accept = match line.chars().next() {
+            Some('+') => true,
+            Some('-') => false,
+            None | Some('#') => continue,
+            Some(_) => {
+                errors.push(DirectiveError {
+                    line: idx + 1,
+                    message: String::from("expected `+`, `-` or `#` at the start of the line"),
+                });
+                continue;
+            }
+        };
+        expectations.push(Expectation {
+            line: idx + 1,
+            rule: None,
+            word: line[1..].to_string(),
+            accept,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(expectations)
+    } else {
+        Err(errors)
+    }
+}
+
+/// An invalid test directive in a rules file, or an invalid line of an annotated test file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DirectiveError {
+    /// line number in the file, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+/// returns whether a line of a rules file is a test directive, which may be indented
+pub fn is_directive(line: &str) -> bool {
+    line.trim_start().starts_with('@')
+}
+
+/// replaces all test directives in a rules file with whitespace
+///
+/// The positions of all other characters stay the same, so locations of syntax errors in the
+/// result are also valid for the original source.
+pub fn strip_directives(source: &str) -> String {
+    source
+        .split('\n')
+        .map(|line| {
+            if is_directive(line) {
+                line.chars().map(|_| ' ').collect()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// reads the test directives of a rules file
+///
+/// A directive is a line of the form `@accept <rule> word` or `@reject <rule> word`.
+/// The word starts after the single space following the rule name and extends to the end of the
+/// line, so `@accept <rule>` expects the empty word to be accepted.
+pub fn directives(source: &str) -> Result<Vec<Expectation>, Vec<DirectiveError>> {
+    let mut expectations = Vec::new();
+    let mut errors = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        if !is_directive(line) {
+            continue;
+        }
+        let line_number = idx + 1;
+        let mut error = |message: &str| {
+            errors.push(DirectiveError {
+                line: line_number,
+                message: message.to_string(),
+            })
+        };
+
+        let line = line.trim_start();
+        let (accept, rest) = if let Some(rest) = line.strip_prefix("@accept") {
+            (true, rest)
+        } else if let Some(rest) = line.strip_prefix("@reject") {
+            (false, rest)
+        } else {
+            error("unknown directive, expected `@accept` or `@reject`");
+            continue;
+        };
+
+        let rest = rest.trim_start();
+        let rest = match rest.strip_prefix('<') {
+            Some(rest) => rest,
+            None => {
+                error("expected a rule name in angle brackets");
+                continue;
+            }
+        };
+        let (rule, word) = match rest.find('>') {
+            Some(end) => (&rest[..end], &rest[end + 1..]),
+            None => {
+                error("expected `>` after the rule name");
+                continue;
+            }
+        };
+        let word = word.strip_prefix(' ').unwrap_or(word);
+
+        expectations.push(Expectation {
+            line: line_number,
+            rule: Some(rule.to_string()),
+            word: word.to_string(),
+            accept,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(expectations)
+    } else {
+        Err(errors)
+    }
+}
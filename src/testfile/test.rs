@@ -0,0 +1,122 @@
+use super::*;
+
+#[test]
+fn annotated_files() {
+    assert!(is_annotated(Path::new("rules/scream.expect")));
+    assert!(!is_annotated(Path::new("rules/scream.test")));
+    assert!(!is_annotated(Path::new("rules/expect")));
+    assert!(!is_annotated(Path::new("signed-integers")));
+}
+
+#[test]
+fn annotated() {
+    let source = "# scream\n+aAa\n-foo\n\n+\n- a";
+    assert_eq!(
+        parse(source),
+        Ok(vec![
+            Expectation {
+                line: 2,
+                rule: None,
+                word: "aAa".into(),
+                accept: true
+            },
+            Expectation {
+                line: 3,
+                rule: None,
+                word: "foo".into(),
+                accept: false
+            },
+            Expectation {
+                line: 5,
+                rule: None,
+                word: "".into(),
+                accept: true
+            },
+            Expectation {
+                line: 6,
+                rule: None,
+                word: " a".into(),
+                accept: false
+            },
+        ])
+    );
+}
+
+#[test]
+fn malformed_lines() {
+    let errors = parse("oops\n+a\n# ok\n foo\n-b").unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+        vec![1, 4]
+    );
+}
+
+#[test]
+fn inline_directives() {
+    let source = "<a> <= a | b\n@accept <a> a\n  @reject <a>  c\n@accept <a>\n<b> <= <a>";
+    assert_eq!(
+        directives(source),
+        Ok(vec![
+            Expectation {
+                line: 2,
+                rule: Some("a".into()),
+                word: "a".into(),
+                accept: true
+            },
+            Expectation {
+                line: 3,
+                rule: Some("a".into()),
+                word: " c".into(),
+                accept: false
+            },
+            Expectation {
+                line: 4,
+                rule: Some("a".into()),
+                word: "".into(),
+                accept: true
+            },
+        ])
+    );
+
+    let stripped = strip_directives(source);
+    assert_eq!(stripped.len(), source.len());
+    assert_eq!(stripped.lines().nth(1), Some(" ".repeat(13).as_str()));
+    assert_eq!(stripped.lines().nth(4), Some("<b> <= <a>"));
+}
+
+#[test]
+fn invalid_directives() {
+    let errors = directives("@acept <a> a\n@reject a\n@accept <a a\n@reject <a> ok").unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+}
+
+/// verdicts of watch mode are kept for expectations that only moved to another line
+#[test]
+fn keys_survive_inserted_lines() {
+    use crate::watch::Verdicts;
+    use std::collections::HashMap;
+
+    let run = |verdicts: &mut Verdicts, source: &str, max: usize| {
+        let mut next = HashMap::new();
+        let changed = directives(source)
+            .unwrap()
+            .iter()
+            .filter(|exp| {
+                let changed = verdicts.update(&exp.key(), exp.word.len() <= max, &mut next);
+                changed && verdicts.seen(&exp.key())
+            })
+            .map(|exp| exp.word.clone())
+            .collect::<Vec<_>>();
+        verdicts.finish(next);
+        changed
+    };
+    let mut verdicts = Verdicts::new();
+    let source = "<s> <= a {a}\n@accept <s> aa\n@reject <s> aaa\n@reject <s> aa";
+    assert!(run(&mut verdicts, source, 2).is_empty());
+    let inserted = source.replacen('\n', "\n@accept <s> a\n", 1);
+    assert!(run(&mut verdicts, &inserted, 2).is_empty());
+    assert_eq!(run(&mut verdicts, &inserted, 3), vec!["aaa"]);
+}
@@ -0,0 +1,478 @@
+use crate::bnf::{BnfPart, BnfRule};
+use crate::chomsky;
+use crate::compare::Comparison;
+use crate::error::Error;
+use crate::ll1::Analysis;
+
+#[cfg(test)]
+mod test;
+
+/// A sequence of parts, one alternative of a rule
+type Alternative = Vec<BnfPart>;
+
+/// splits a sequence into alternatives, until each of them starts with a literal or a rule
+///
+/// Choices and optional parts at the beginning are distributed, and repetitions at the
+/// beginning are unrolled once, so no rule can hide behind them.
+fn expand(sequence: &[BnfPart]) -> Vec<Alternative> {
+    use BnfPart::*;
+    let (first, rest) = match sequence.split_first() {
+        Some(split) => split,
+        None => return vec![vec![]],
+    };
+    let prepend = |head: &[BnfPart]| {
+        let mut alt = head.to_vec();
+        alt.extend(rest.iter().cloned());
+        alt
+    };
+    match first {
+        Empty => expand(rest),
+        Literal(s) if s.is_empty() => expand(rest),
+        Literal(_) | Rule(_) => vec![sequence.to_vec()],
+        Concat(parts) => expand(&prepend(parts)),
+        Choice(parts) => parts
+            .iter()
+            .flat_map(|p| expand(&prepend(std::slice::from_ref(p))))
+            .collect(),
+        Repeat(part) => {
+            let mut alts = expand(rest);
+            for mut alt in expand(std::slice::from_ref(part)) {
+                if alt.is_empty() {
+                    continue;
+                }
+                alt.push(first.clone());
+                alt.extend(rest.iter().cloned());
+                alts.push(alt);
+            }
+            alts
+        }
+    }
+}
+
+/// turns a sequence back into a single part
+fn sequence(mut alt: Alternative) -> BnfPart {
+    match alt.len() {
+        0 => BnfPart::Empty,
+        1 => alt.remove(0),
+        _ => BnfPart::Concat(alt),
+    }
+}
+
+/// turns alternatives back into a single part
+fn choice(alts: Vec<Alternative>) -> BnfPart {
+    let mut parts = Vec::new();
+    for part in alts.into_iter().map(sequence) {
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    // keep `[x]` readable by putting the empty alternative last
+    if let Some(empty) = parts.iter().position(|p| *p == BnfPart::Empty) {
+        parts.remove(empty);
+        parts.push(BnfPart::Empty);
+    }
+    match parts.len() {
+        1 => parts.remove(0),
+        _ => BnfPart::Choice(parts),
+    }
+}
+
+/// Rewrites left recursive rules, such that they can be parsed by recursive descent
+///
+/// Uses the standard algorithm for indirect left recursion: the rules involved are ordered, a
+/// rule starting with a previous one gets the definition of that rule substituted, and direct
+/// left recursion `<a> <= <a> x | y` is replaced by the repetition `<a> <= y {x}`.
+/// Other rules are returned unchanged. Fails if the rules can't produce any word without
+/// recursion, or if the recursion is hidden behind a rule that can be empty.
+pub fn eliminate_left_recursion(rules: &[BnfRule]) -> Result<Vec<BnfRule>, String> {
+    let start = match rules.first() {
+        Some(rule) => &rule.name,
+        None => return Ok(Vec::new()),
+    };
+    let analysis = Analysis::new(rules, start);
+    let recursive = analysis.left_recursion();
+    if recursive.is_empty() {
+        return Ok(rules.to_vec());
+    }
+
+    // rules involved in a cycle, in order of their definition
+    let mut involved = recursive.iter().flatten().cloned().collect::<Vec<_>>();
+    involved.sort_unstable();
+    involved.dedup();
+
+    let mut defs = analysis.defs.clone();
+    for (i, &r) in involved.iter().enumerate() {
+        let mut alts = expand(std::slice::from_ref(&defs[r]));
+        for &previous in involved[..i].iter() {
+            let name = &analysis.names[previous];
+            alts = alts
+                .into_iter()
+                .flat_map(|alt| match alt.first() {
+                    Some(BnfPart::Rule(n)) if n == name => {
+                        let mut substituted = vec![defs[previous].clone()];
+                        substituted.extend(alt[1..].iter().cloned());
+                        expand(&substituted)
+                    }
+                    _ => vec![alt],
+                })
+                .collect();
+        }
+
+        let name = &analysis.names[r];
+        let (recursive, base): (Vec<_>, Vec<_>) = alts
+            .into_iter()
+            .partition(|alt| matches!(alt.first(), Some(BnfPart::Rule(n)) if n == name));
+        if base.is_empty() {
+            return Err(format!(
+                "<{}> can't produce any word without recursion",
+                name
+            ));
+        }
+        let repeated = recursive
+            .into_iter()
+            .map(|alt| alt[1..].to_vec())
+            .filter(|alt| !alt.is_empty())
+            .collect::<Vec<_>>();
+        defs[r] = if repeated.is_empty() {
+            choice(base)
+        } else {
+            BnfPart::Concat(vec![
+                choice(base),
+                BnfPart::Repeat(Box::new(choice(repeated))),
+            ])
+        };
+    }
+
+    // rules keep their position, the definitions of rewritten rules are merged into one
+    let mut result = Vec::new();
+    for rule in rules.iter() {
+        let r = analysis.rule(&rule.name).unwrap();
+        if !involved.contains(&r) {
+            result.push(rule.clone());
+        } else if !result.iter().any(|x: &BnfRule| x.name == rule.name) {
+            result.push(BnfRule {
+                name: rule.name.clone(),
+                def: defs[r].clone(),
+            });
+        }
+    }
+
+    let remaining = Analysis::new(&result, start);
+    if let Some(cycle) = remaining.left_recursion().first() {
+        return Err(format!(
+            "the left recursion {} goes through a rule that can be empty",
+            remaining.format_cycle(cycle)
+        ));
+    }
+    Ok(result)
+}
+
+/// The first symbol of an alternative, that alternatives are factored by
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Head {
+    Char(char),
+    Rule(String),
+}
+
+/// splits off the first character or rule of an alternative, if it starts with one
+fn split_head(alt: &[BnfPart]) -> Option<(Head, Alternative)> {
+    use BnfPart::*;
+    let (first, rest) = alt.split_first()?;
+    match first {
+        Literal(s) if !s.is_empty() => {
+            let mut chars = s.chars();
+            let head = chars.next().unwrap();
+            let mut tail = Vec::new();
+            if !chars.as_str().is_empty() {
+                tail.push(Literal(chars.as_str().to_string()));
+            }
+            tail.extend(rest.iter().cloned());
+            Some((Head::Char(head), tail))
+        }
+        Rule(name) => Some((Head::Rule(name.clone()), rest.to_vec())),
+        Empty | Literal(_) => split_head(rest),
+        Concat(parts) => {
+            let mut alt = parts.clone();
+            alt.extend(rest.iter().cloned());
+            split_head(&alt)
+        }
+        Choice(_) | Repeat(_) => None,
+    }
+}
+
+/// turns the heads of a common prefix back into parts, merging characters into literals
+fn prefix_parts(heads: Vec<Head>) -> Alternative {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    for head in heads {
+        match head {
+            Head::Char(c) => literal.push(c),
+            Head::Rule(name) => {
+                if !literal.is_empty() {
+                    parts.push(BnfPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(BnfPart::Rule(name));
+            }
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(BnfPart::Literal(literal));
+    }
+    parts
+}
+
+/// the alternatives of a part, with nested choices flattened
+fn alternatives(part: BnfPart) -> Vec<Alternative> {
+    match part {
+        BnfPart::Choice(parts) => parts.into_iter().flat_map(alternatives).collect(),
+        BnfPart::Concat(parts) => vec![parts],
+        BnfPart::Empty => vec![vec![]],
+        part => vec![vec![part]],
+    }
+}
+
+fn dedup(alts: Vec<Alternative>) -> Vec<Alternative> {
+    let mut unique = Vec::new();
+    for alt in alts {
+        if !unique.contains(&alt) {
+            unique.push(alt);
+        }
+    }
+    unique
+}
+
+struct Factor<'a> {
+    analysis: &'a Analysis,
+    /// rules that are never replaced by their definition, as that wouldn't terminate
+    left_recursive: &'a [usize],
+    /// number of alternatives that may still be rewritten to reveal a common prefix, which
+    /// guarantees termination for recursive rules and repetitions
+    budget: usize,
+}
+
+impl Factor<'_> {
+    fn part(&mut self, part: &BnfPart) -> BnfPart {
+        use BnfPart::*;
+        match part {
+            Concat(parts) => sequence(parts.iter().map(|p| self.part(p)).collect()),
+            Repeat(inner) => Repeat(Box::new(self.part(inner))),
+            Choice(parts) => {
+                let alts = parts
+                    .iter()
+                    .flat_map(|p| alternatives(self.part(p)))
+                    .collect();
+                self.choice(alts)
+            }
+            part => part.clone(),
+        }
+    }
+
+    /// whether `alt` can start with the same character as one of `others` with another head
+    fn overlaps(&self, alt: &[BnfPart], others: &[Alternative]) -> bool {
+        let first = self.analysis.first_part(&sequence(alt.to_vec()));
+        let head = split_head(alt).map(|h| h.0);
+        others.iter().any(|other| {
+            (head.is_none() || split_head(other).map(|h| h.0) != head)
+                && !first.is_disjoint(&self.analysis.first_part(&sequence(other.clone())))
+        })
+    }
+
+    /// replaces the leading rule reference of `alt` by its definition, or distributes its
+    /// leading choice, or returns `None` if neither is possible
+    fn expand_head(&self, alt: &[BnfPart]) -> Option<Vec<Alternative>> {
+        match split_head(alt) {
+            None if !alt.is_empty() => Some(expand(alt)),
+            Some((Head::Rule(name), rest)) => self
+                .analysis
+                .rule(&name)
+                .filter(|r| !self.left_recursive.contains(r))
+                .map(|r| {
+                    let mut inlined = vec![self.analysis.defs[r].clone()];
+                    inlined.extend(rest);
+                    expand(&inlined)
+                }),
+            _ => None,
+        }
+    }
+
+    /// expands `alt` until one of its alternatives shares its head with one of `others`
+    ///
+    /// Returns `None` if that doesn't happen within the budget, in which case nothing of the
+    /// budget is used.
+    fn reveal_one(&mut self, alt: &[BnfPart], others: &[Alternative]) -> Option<Vec<Alternative>> {
+        let heads = others
+            .iter()
+            .filter_map(|o| split_head(o).map(|h| h.0))
+            .collect::<Vec<_>>();
+        let mut budget = self.budget;
+        let mut expanded = vec![alt.to_vec()];
+        while budget > 0 {
+            let (i, replacement) = expanded.iter().enumerate().find_map(|(i, a)| {
+                self.expand_head(a)
+                    .filter(|_| self.overlaps(a, others))
+                    .map(|r| (i, r))
+            })?;
+            expanded.splice(i..=i, replacement);
+            budget -= 1;
+            if expanded
+                .iter()
+                .any(|a| split_head(a).is_some_and(|h| heads.contains(&h.0)))
+            {
+                self.budget = budget;
+                return Some(expanded);
+            }
+        }
+        None
+    }
+
+    /// makes alternatives that start with the same characters visible, by distributing
+    /// choices and replacing rule references with their definition
+    ///
+    /// An alternative is only rewritten if that gives it the same head as another alternative,
+    /// such that the rewrite is undone by extracting the common prefix.
+    fn reveal(&mut self, mut alts: Vec<Alternative>) -> Vec<Alternative> {
+        let mut i = 0;
+        while i < alts.len() {
+            let others = alts
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, a)| a.clone())
+                .collect::<Vec<_>>();
+            let replacement = if self.overlaps(&alts[i], &others) {
+                self.reveal_one(&alts[i], &others)
+            } else {
+                None
+            };
+            match replacement {
+                Some(replacement) => {
+                    alts.splice(i..=i, replacement);
+                }
+                None => i += 1,
+            }
+        }
+        alts
+    }
+
+    fn choice(&mut self, alts: Vec<Alternative>) -> BnfPart {
+        let alts = dedup(self.reveal(dedup(alts)));
+
+        // alternatives grouped by their head, in order of their first appearance
+        let mut groups: Vec<(Option<Head>, Vec<Alternative>)> = Vec::new();
+        for alt in alts {
+            let head = split_head(&alt).map(|h| h.0);
+            match groups.iter_mut().find(|g| head.is_some() && g.0 == head) {
+                Some(group) => group.1.push(alt),
+                None => groups.push((head, vec![alt])),
+            }
+        }
+
+        let mut result = Vec::new();
+        for (_, mut group) in groups {
+            if group.len() == 1 {
+                result.push(group.remove(0));
+                continue;
+            }
+            let mut prefix = Vec::new();
+            loop {
+                let split = group.iter().map(|a| split_head(a)).collect::<Vec<_>>();
+                let head = match &split[0] {
+                    Some((head, _)) => head.clone(),
+                    None => break,
+                };
+                if split
+                    .iter()
+                    .any(|s| s.as_ref().map(|s| &s.0) != Some(&head))
+                {
+                    break;
+                }
+                prefix.push(head);
+                group = split.into_iter().map(|s| s.unwrap().1).collect();
+            }
+            let mut alt = prefix_parts(prefix);
+            match self.choice(group) {
+                BnfPart::Empty => {}
+                BnfPart::Concat(parts) => alt.extend(parts),
+                rest => alt.push(rest),
+            }
+            result.push(alt);
+        }
+        choice(result)
+    }
+}
+
+/// Extracts common prefixes of alternatives, e.g. `a b | a c` becomes `a (b | c)`
+///
+/// Rule references at the beginning of an alternative are replaced by their definition if
+/// that reveals a common prefix with another alternative, unless they are left recursive.
+/// Rules that don't change are returned as they are, the definitions of the others are merged
+/// into one. Rules whose definition would grow by more than a small factor are kept as well.
+pub fn left_factor(rules: &[BnfRule]) -> Vec<BnfRule> {
+    /// maximum number of rule references that are replaced within a rule
+    const BUDGET: usize = 8;
+    /// maximum factor by which the written definition of a rule may grow, otherwise the rule
+    /// is kept as it is
+    const GROWTH: usize = 3;
+
+    let start = match rules.first() {
+        Some(rule) => &rule.name,
+        None => return Vec::new(),
+    };
+    let analysis = Analysis::new(rules, start);
+    let left_recursive = analysis.left_recursive();
+    let factored = analysis
+        .defs
+        .iter()
+        .map(|def| {
+            let mut factor = Factor {
+                analysis: &analysis,
+                left_recursive: &left_recursive,
+                budget: BUDGET,
+            };
+            let factored = factor.part(def);
+            if factored.to_string().len() > GROWTH * def.to_string().len() {
+                def.clone()
+            } else {
+                factored
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut result = Vec::new();
+    for rule in rules.iter() {
+        let r = analysis.rule(&rule.name).unwrap();
+        if factored[r] == analysis.defs[r] {
+            result.push(rule.clone());
+        } else if !result.iter().any(|x: &BnfRule| x.name == rule.name) {
+            result.push(BnfRule {
+                name: rule.name.clone(),
+                def: factored[r].clone(),
+            });
+        }
+    }
+    result
+}
+
+/// Compares every rule of `original` with the rule of the same name in `transformed`
+///
+/// Returns the comparisons of the rules that accept different words, among the first `limit`
+/// words of each.
+pub fn differences(
+    original: &[BnfRule],
+    transformed: &[BnfRule],
+    limit: usize,
+) -> Result<Vec<(String, Comparison)>, Error> {
+    let mut differences = Vec::new();
+    for (i, name) in original.iter().map(|r| &r.name).enumerate() {
+        if original[..i].iter().any(|r| &r.name == name) {
+            continue;
+        }
+        let a = chomsky::Grammar::from_rules(original, Some(name))?;
+        let b = chomsky::Grammar::from_rules(transformed, Some(name))?;
+        let comparison = Comparison::from_grammars(a, b, limit);
+        if !comparison.first.is_empty() || !comparison.second.is_empty() {
+            differences.push((name.clone(), comparison));
+        }
+    }
+    Ok(differences)
+}
@@ -0,0 +1,125 @@
+use super::*;
+use crate::chomsky::Grammar;
+use crate::compare::Comparison;
+
+fn rules(source: &str) -> Vec<BnfRule> {
+    crate::bnf::from_source(source).unwrap()
+}
+
+fn to_source(rules: &[BnfRule]) -> String {
+    rules
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// checks that both sets of rules accept the same words, up to a limit
+fn assert_equivalent(a: &[BnfRule], b: &[BnfRule]) {
+    let names = a.iter().map(|r| &r.name).collect::<Vec<_>>();
+    for name in names {
+        let ga = Grammar::from_rules(a, Some(name)).unwrap();
+        let gb = Grammar::from_rules(b, Some(name)).unwrap();
+        let comparison = Comparison::from_grammars(ga, gb, 300);
+        assert!(comparison.first.is_empty(), "<{}>: {:?}", name, comparison);
+        assert!(comparison.second.is_empty(), "<{}>: {:?}", name, comparison);
+    }
+}
+
+#[test]
+fn direct() {
+    let r = rules("<e> <= <e>+<t> | <e>-<t> | <t>\n<t> <= x | y");
+    let result = eliminate_left_recursion(&r).unwrap();
+    assert_eq!(
+        to_source(&result),
+        "<e> <= <t> {+ <t> | - <t>}\n<t> <= x | y"
+    );
+    assert_equivalent(&r, &result);
+}
+
+#[test]
+fn indirect() {
+    let source = std::fs::read_to_string("rules/binary-div3").unwrap();
+    let r = rules(&source);
+    let result = eliminate_left_recursion(&r).unwrap();
+    assert!(Analysis::new(&result, "0").left_recursive().is_empty());
+    // the text of <comment> is kept
+    assert_eq!(result.last(), r.last());
+    assert_equivalent(&r, &result);
+}
+
+#[test]
+fn hidden_by_optional() {
+    let r = rules("<a> <= [x] <a> y | {z} <b>\n<b> <= b | <a> b");
+    let result = eliminate_left_recursion(&r).unwrap();
+    assert!(Analysis::new(&result, "a").left_recursive().is_empty());
+    assert_equivalent(&r, &result);
+}
+
+#[test]
+fn failures() {
+    assert!(eliminate_left_recursion(&rules("<a> <= <a> x")).is_err());
+    assert!(eliminate_left_recursion(&rules("<a> <= <b> <a> x | y\n<b> <= [b]")).is_err());
+}
+
+fn factored(source: &str) -> String {
+    let r = rules(source);
+    let result = left_factor(&r);
+    assert!(differences(&r, &result, 300).unwrap().is_empty());
+    to_source(&result)
+}
+
+#[test]
+fn common_prefix() {
+    assert_eq!(factored("<s> <= ab | ac"), "<s> <= a (b | c)");
+    assert_eq!(factored("<s> <= a | ab"), "<s> <= a [b]");
+    assert_eq!(
+        factored("<s> <= <x> b | <x> c\n<x> <= x"),
+        "<s> <= <x> (b | c)\n<x> <= x"
+    );
+    assert_eq!(factored("<s> <= abc | abd | e"), "<s> <= ab (c | d) | e");
+}
+
+#[test]
+fn across_rules() {
+    assert_eq!(
+        factored("<s> <= <a> c | a d\n<a> <= a b"),
+        "<s> <= a (b c | d)\n<a> <= ab"
+    );
+    assert_eq!(factored("<s> <= [a] b | a c"), "<s> <= a (b | c) | b");
+}
+
+#[test]
+fn terminates() {
+    factored("<s> <= {a} | a {a} b");
+    factored("<s> <= <s> a | a <s> | a");
+    factored("<s> <= <t> | a\n<t> <= <s> b | a");
+}
+
+#[test]
+fn equivalent() {
+    for file in ["rules/binary-div3", "rules/scream", "rules/long-scream"].iter() {
+        let source = std::fs::read_to_string(file).unwrap();
+        factored(&source);
+    }
+}
+
+#[test]
+fn bounded_growth() {
+    let r = rules(&std::fs::read_to_string("rules/java-subset").unwrap());
+    let before = to_source(&r).len();
+    let after = to_source(&left_factor(&r)).len();
+    assert!(
+        after <= 2 * before,
+        "grew from {} to {} bytes",
+        before,
+        after
+    );
+}
+
+#[test]
+fn inlines_only_to_factor() {
+    // <a> is left recursive, so inlining <b> doesn't reveal a common prefix with `xz`
+    let source = "<s> <= <b> | xz\n<b> <= <a> q\n<a> <= <a> y | x";
+    assert_eq!(factored(source), source);
+}
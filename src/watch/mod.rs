@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod test;
+
+/// time to wait between two polls
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Polls a set of files for modifications
+///
+/// Files that can't be read are treated as having no modification time, so deleting and
+/// recreating a file also counts as a change.
+pub struct Watcher {
+    /// watched files, together with the last seen modification time
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
+
+impl Watcher {
+    /// builds a new watcher that considers the current state of the files as unchanged
+    pub fn new<P: AsRef<Path>>(files: &[P]) -> Self {
+        let files = files
+            .iter()
+            .map(|f| (f.as_ref().to_path_buf(), modified(f.as_ref())))
+            .collect();
+        Self { files }
+    }
+
+    /// returns whether any file changed since the last call
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (file, time) in self.files.iter_mut() {
+            let now = modified(file);
+            if now != *time {
+                *time = now;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// blocks until one of the files changes
+    pub fn wait(&mut self) {
+        while !self.changed() {
+            std::thread::sleep(INTERVAL);
+        }
+        // editors often write a file in several steps, give them time to finish
+        std::thread::sleep(INTERVAL);
+        self.changed();
+    }
+}
+
+/// How the errors of a run differ from the ones of the previous run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorChange {
+    /// the previous run had no errors
+    New,
+    /// the previous run reported exactly the same errors
+    Same,
+    /// the previous run reported other errors
+    Different,
+    /// the previous run had errors, but this one has none
+    Fixed,
+}
+
+/// Verdicts of the previous run of a command, used to highlight changes in watch mode
+#[derive(Debug, Default)]
+pub struct Verdicts {
+    /// whether there was a previous successful run to compare against
+    initialized: bool,
+    verdicts: HashMap<String, bool>,
+    /// errors reported by the previous run, `None` before the first run
+    errors: Option<Vec<String>>,
+}
+
+impl Verdicts {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// records a verdict and returns whether it differs from the one of the previous run
+    pub fn update(&mut self, key: &str, verdict: bool, next: &mut HashMap<String, bool>) -> bool {
+        next.insert(key.to_string(), verdict);
+        self.initialized && self.verdicts.get(key) != Some(&verdict)
+    }
+
+    /// whether the previous run recorded a verdict for the key
+    pub fn seen(&self, key: &str) -> bool {
+        self.verdicts.contains_key(key)
+    }
+
+    /// keys of the previous run that weren't recorded in `next`, sorted
+    pub fn removed(&self, next: &HashMap<String, bool>) -> Vec<&str> {
+        let mut removed = self
+            .verdicts
+            .keys()
+            .filter(|key| !next.contains_key(*key))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+        removed
+    }
+
+    /// records the errors reported by a run and returns how they differ from the previous run
+    ///
+    /// Returns `None` for the first run, and if neither run reported errors.
+    pub fn errors(&mut self, errors: Vec<String>) -> Option<ErrorChange> {
+        let previous = self.errors.replace(errors);
+        let (previous, errors) = (previous?, self.errors.as_ref().unwrap());
+        match (previous.is_empty(), errors.is_empty()) {
+            (true, true) => None,
+            (true, false) => Some(ErrorChange::New),
+            (false, true) => Some(ErrorChange::Fixed),
+            (false, false) if previous == *errors => Some(ErrorChange::Same),
+            (false, false) => Some(ErrorChange::Different),
+        }
+    }
+
+    /// makes the verdicts of the run that just ended the ones to compare against
+    pub fn finish(&mut self, next: HashMap<String, bool>) {
+        self.initialized = true;
+        self.verdicts = next;
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::scratch::scratch;
+use std::fs::File;
+use std::time::UNIX_EPOCH;
+
+fn touch(file: &Path, secs: u64) {
+    let f = File::create(file).unwrap();
+    f.set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap();
+}
+
+#[test]
+fn notices_changes() {
+    let (file, _guard) = scratch("watch-changes", "rules");
+    touch(&file, 1000);
+    let mut watcher = Watcher::new(&[&file]);
+    assert!(!watcher.changed());
+
+    touch(&file, 2000);
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    std::fs::remove_file(&file).unwrap();
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    touch(&file, 2000);
+    assert!(watcher.changed());
+}
+
+#[test]
+fn missing_files() {
+    let (file, _guard) = scratch("watch-missing", "rules");
+    let mut watcher = Watcher::new(&[&file]);
+    assert!(!watcher.changed());
+    touch(&file, 1000);
+    assert!(watcher.changed());
+}
+
+#[test]
+fn verdicts() {
+    let mut verdicts = Verdicts::new();
+
+    // nothing is marked as changed in the first run
+    let mut next = HashMap::new();
+    assert!(!verdicts.update("a", true, &mut next));
+    assert!(!verdicts.update("b", false, &mut next));
+    verdicts.finish(next);
+
+    let mut next = HashMap::new();
+    assert!(!verdicts.update("a", true, &mut next));
+    assert!(verdicts.update("b", true, &mut next));
+    assert!(verdicts.update("c", false, &mut next));
+    verdicts.finish(next);
+
+    // only the verdicts of the last run count
+    let mut next = HashMap::new();
+    assert!(!verdicts.update("b", true, &mut next));
+    assert!(!verdicts.update("c", false, &mut next));
+    assert!(verdicts.update("a", false, &mut next));
+    verdicts.finish(next);
+}
+
+#[test]
+fn removed_verdicts() {
+    let mut verdicts = Verdicts::new();
+    let mut next = HashMap::new();
+    verdicts.update("b", true, &mut next);
+    verdicts.update("a", true, &mut next);
+    assert!(verdicts.removed(&next).is_empty());
+    verdicts.finish(next);
+
+    let mut next = HashMap::new();
+    verdicts.update("c", true, &mut next);
+    assert_eq!(verdicts.removed(&next), vec!["a", "b"]);
+}
+
+#[test]
+fn error_changes() {
+    let mut verdicts = Verdicts::new();
+    let errors = |e: &[&str]| e.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    // nothing is marked in the first run
+    assert_eq!(verdicts.errors(errors(&["a"])), None);
+    assert_eq!(verdicts.errors(errors(&[])), Some(ErrorChange::Fixed));
+    assert_eq!(verdicts.errors(errors(&["a"])), Some(ErrorChange::New));
+    assert_eq!(verdicts.errors(errors(&["a"])), Some(ErrorChange::Same));
+    assert_eq!(
+        verdicts.errors(errors(&["a", "b"])),
+        Some(ErrorChange::Different)
+    );
+    assert_eq!(verdicts.errors(errors(&[])), Some(ErrorChange::Fixed));
+    assert_eq!(verdicts.errors(errors(&[])), None);
+}